@@ -6,15 +6,21 @@
  */
 
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Context;
+use anyhow::Result;
 use edenfs_error::ConnectError;
 use fbinit::FacebookInit;
 use futures::future::BoxFuture;
+use futures::future::Future;
 use futures::future::FutureExt;
 use futures::future::Shared;
+use futures::stream;
+use futures::stream::Stream;
+use futures::stream::StreamExt;
 use thrift_streaming_clients::StreamingEdenServiceExt;
 use thrift_streaming_thriftclients::make_StreamingEdenServiceExt_thriftclient;
 use thriftclient::ThriftChannel;
@@ -24,6 +30,21 @@ use crate::client::connector::Connector;
 use crate::client::connector::DEFAULT_CONN_TIMEOUT;
 use crate::client::connector::DEFAULT_RECV_TIMEOUT;
 
+/// An item produced by a [`StreamingEdenFsConnector::subscribe`] stream.
+/// `Reconnected` is emitted whenever the underlying connection had to be
+/// re-established after an error or an unexpected end of stream, so
+/// consumers that maintain derived state know to resync it rather than
+/// assuming the feed has been continuous.
+pub enum SubscribeItem<T> {
+    Item(T),
+    Reconnected,
+}
+
+enum SubscribeState<S> {
+    Connect { is_reconnect: bool },
+    Streaming { stream: S },
+}
+
 pub type StreamingEdenFsThriftClient =
     Arc<dyn StreamingEdenServiceExt<ThriftChannel> + Send + Sync + 'static>;
 pub type StreamingEdenFsThriftClientFuture =
@@ -83,3 +104,117 @@ impl Connector for StreamingEdenFsConnector {
         .shared()
     }
 }
+
+/// Backoff between reconnect attempts inside `subscribe`, so a daemon that's
+/// briefly unreachable (e.g. mid-restart) doesn't get hammered with connect
+/// attempts.
+const SUBSCRIBE_RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+
+impl StreamingEdenFsConnector {
+    /// Open a durable, auto-reconnecting subscription over this connector.
+    ///
+    /// `establish` is called with a freshly-connected thrift client each time
+    /// a subscription needs to be (re-)opened, and should invoke the desired
+    /// streaming Thrift method and return its item stream. If connecting
+    /// fails, `establish` fails, or the returned stream ends before the
+    /// caller drops the returned stream, `subscribe` transparently
+    /// reconnects (honoring `conn_timeout`/`recv_timeout`) and re-invokes
+    /// `establish`, surfacing a `SubscribeItem::Reconnected` marker so
+    /// consumers can resync any state they derived from the feed. This lets
+    /// callers watch mount/journal changes without each reimplementing
+    /// backoff and re-subscription.
+    pub fn subscribe<T, S, Fut, F>(
+        &self,
+        conn_timeout: Option<Duration>,
+        recv_timeout: Option<Duration>,
+        establish: F,
+    ) -> Pin<Box<dyn Stream<Item = Result<SubscribeItem<T>>> + Send>>
+    where
+        F: Fn(StreamingEdenFsThriftClient) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S>> + Send + 'static,
+        S: Stream<Item = Result<T>> + Unpin + Send + 'static,
+        T: Send + 'static,
+    {
+        let fb = self.fb;
+        let socket_file = self.socket_file.clone();
+        let establish = Arc::new(establish);
+
+        stream::unfold(
+            SubscribeState::Connect { is_reconnect: false },
+            move |mut state| {
+                let establish = establish.clone();
+                let socket_file = socket_file.clone();
+                async move {
+                    loop {
+                        match state {
+                            SubscribeState::Connect { is_reconnect } => {
+                                let connector = StreamingEdenFsConnector {
+                                    fb,
+                                    socket_file: socket_file.clone(),
+                                };
+                                let client = match connector.connect(conn_timeout, recv_timeout).await
+                                {
+                                    Ok(client) => client,
+                                    Err(e) => {
+                                        tracing::warn!(
+                                            "subscribe: failed to (re)connect, retrying: {:?}",
+                                            e
+                                        );
+                                        tokio::time::sleep(SUBSCRIBE_RECONNECT_BACKOFF).await;
+                                        state = SubscribeState::Connect { is_reconnect: true };
+                                        continue;
+                                    }
+                                };
+                                match establish(client).await {
+                                    Ok(new_stream) => {
+                                        if is_reconnect {
+                                            return Some((
+                                                Ok(SubscribeItem::Reconnected),
+                                                SubscribeState::Streaming { stream: new_stream },
+                                            ));
+                                        }
+                                        state = SubscribeState::Streaming { stream: new_stream };
+                                        continue;
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!(
+                                            "subscribe: failed to establish subscription, retrying: {:?}",
+                                            e
+                                        );
+                                        tokio::time::sleep(SUBSCRIBE_RECONNECT_BACKOFF).await;
+                                        state = SubscribeState::Connect { is_reconnect: true };
+                                        continue;
+                                    }
+                                }
+                            }
+                            SubscribeState::Streaming { mut stream } => match stream.next().await {
+                                Some(Ok(item)) => {
+                                    return Some((
+                                        Ok(SubscribeItem::Item(item)),
+                                        SubscribeState::Streaming { stream },
+                                    ));
+                                }
+                                Some(Err(e)) => {
+                                    tracing::warn!(
+                                        "subscribe: stream error, reconnecting: {:?}",
+                                        e
+                                    );
+                                    state = SubscribeState::Connect { is_reconnect: true };
+                                    continue;
+                                }
+                                None => {
+                                    tracing::warn!(
+                                        "subscribe: stream ended unexpectedly, reconnecting"
+                                    );
+                                    state = SubscribeState::Connect { is_reconnect: true };
+                                    continue;
+                                }
+                            },
+                        }
+                    }
+                }
+            },
+        )
+        .boxed()
+    }
+}
@@ -7,6 +7,7 @@
 
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
 use anyhow::anyhow;
 use edenfs_error::EdenFsError;
@@ -19,6 +20,41 @@ use crate::client::EdenFsClient;
 use crate::types::OSName;
 use crate::types::SyncBehavior;
 
+/// Which `GlobParams` fields -- added to the `globFiles` thrift API after
+/// its original release -- the connected EdenFS daemon accepts. Negotiated
+/// lazily against the daemon's actual response (rather than a separate
+/// version probe, since a daemon choking on a field it doesn't recognize
+/// *is* the signal) and cached for the life of the process: a daemon's
+/// feature set can't change mid-run, and re-discovering it on every glob
+/// would be wasteful.
+#[derive(Clone, Copy, Debug)]
+struct GlobCapabilities {
+    predictive_glob: bool,
+    list_only_files: bool,
+    prefetch_metadata: bool,
+}
+
+impl GlobCapabilities {
+    /// Assumed optimistically until a call populating the newer fields
+    /// either succeeds (confirming them) or fails in a way that looks like
+    /// a feature mismatch (downgrading to `LEGACY`).
+    const UNKNOWN: Self = Self {
+        predictive_glob: true,
+        list_only_files: true,
+        prefetch_metadata: true,
+    };
+
+    /// What an EdenFS daemon predating these three `GlobParams` fields
+    /// supports: none of them.
+    const LEGACY: Self = Self {
+        predictive_glob: false,
+        list_only_files: false,
+        prefetch_metadata: false,
+    };
+}
+
+static GLOB_CAPABILITIES: OnceLock<GlobCapabilities> = OnceLock::new();
+
 #[derive(Clone, Debug)]
 pub struct Glob {
     pub matching_files: Vec<Vec<u8>>,
@@ -75,6 +111,29 @@ impl EdenFsClient {
         list_only_files: Option<bool>,
         sync: Option<SyncBehavior>,
     ) -> Result<Glob> {
+        let capabilities = GLOB_CAPABILITIES
+            .get()
+            .copied()
+            .unwrap_or(GlobCapabilities::UNKNOWN);
+
+        // Don't even ask for a field we already know this daemon doesn't
+        // understand -- no point sending it only to strip it out on retry.
+        let predictive_glob = if capabilities.predictive_glob {
+            predictive_glob
+        } else {
+            None
+        };
+        let prefetch_metadata = if capabilities.prefetch_metadata {
+            prefetch_metadata
+        } else {
+            None
+        };
+        let list_only_files = if capabilities.list_only_files {
+            list_only_files
+        } else {
+            None
+        };
+
         let glob_params = GlobParams {
             mountPoint: bytes_from_path(mount_point.as_ref().to_path_buf())?,
             globs: glob_patterns,
@@ -97,16 +156,64 @@ impl EdenFsClient {
             sync: sync.map(Into::into).unwrap_or_default(),
             ..Default::default()
         };
-        self.with_thrift(|thrift| thrift.globFiles(&glob_params))
-            .await
-            .map_err(|err| {
-                EdenFsError::Other(anyhow!(
-                    "Failed invoking globFiles using params='{:?}' with error={:?}'",
-                    glob_params,
+
+        let result = self.with_thrift(|thrift| thrift.globFiles(&glob_params)).await;
+
+        match result {
+            Ok(glob) => {
+                // The daemon accepted every field we sent it, including any
+                // of the three negotiated ones -- confirms `capabilities`.
+                let _ = GLOB_CAPABILITIES.set(capabilities);
+                Ok(glob.into())
+            }
+            Err(err) if GLOB_CAPABILITIES.get().is_none() => {
+                // Capability is still unknown (or optimistically assumed):
+                // this may be an older daemon choking on a field it
+                // doesn't recognize. Downgrade once and retry with only
+                // the fields every supported daemon version understands;
+                // cache whichever set actually worked so later calls skip
+                // straight to it instead of re-discovering it every time.
+                tracing::debug!(
+                    "globFiles failed with newer GlobParams fields populated ({:?}); retrying as a legacy (non-predictive) glob",
                     err
-                ))
-            })
-            .map(Into::into)
+                );
+                let legacy_params = GlobParams {
+                    mountPoint: glob_params.mountPoint.clone(),
+                    globs: glob_params.globs.clone(),
+                    includeDotfiles: glob_params.includeDotfiles,
+                    prefetchFiles: glob_params.prefetchFiles,
+                    suppressFileList: glob_params.suppressFileList,
+                    wantDtype: glob_params.wantDtype,
+                    revisions: glob_params.revisions.clone(),
+                    prefetchMetadata: false,
+                    searchRoot: glob_params.searchRoot.clone(),
+                    background: glob_params.background,
+                    predictiveGlob: None,
+                    listOnlyFiles: false,
+                    sync: glob_params.sync.clone(),
+                    ..Default::default()
+                };
+                match self
+                    .with_thrift(|thrift| thrift.globFiles(&legacy_params))
+                    .await
+                {
+                    Ok(glob) => {
+                        let _ = GLOB_CAPABILITIES.set(GlobCapabilities::LEGACY);
+                        Ok(glob.into())
+                    }
+                    Err(_) => Err(EdenFsError::Other(anyhow!(
+                        "Failed invoking globFiles using params='{:?}' with error={:?}'",
+                        glob_params,
+                        err
+                    ))),
+                }
+            }
+            Err(err) => Err(EdenFsError::Other(anyhow!(
+                "Failed invoking globFiles using params='{:?}' with error={:?}'",
+                glob_params,
+                err
+            ))),
+        }
     }
 
     pub async fn glob_files<P: AsRef<Path>, S: AsRef<Path>>(
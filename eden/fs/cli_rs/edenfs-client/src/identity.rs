@@ -0,0 +1,169 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Signed identities loaded from `config_dir/identities/<name>/identity.toml`,
+//! used to attribute mounts or sign daemon requests.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use chrono::DateTime;
+use chrono::Utc;
+use edenfs_error::EdenFsError;
+use edenfs_error::Result;
+use serde::Deserialize;
+
+const IDENTITIES_DIR: &str = "identities";
+const IDENTITY_FILE: &str = "identity.toml";
+
+/// Errors specific to loading a signed identity, distinguished from a
+/// generic [`EdenFsError::Other`] so callers can tell "doesn't exist" from
+/// "exists but its key rotation is overdue" and react accordingly (e.g.
+/// prompting for a key rotation only in the latter case).
+#[derive(thiserror::Error, Debug)]
+pub enum IdentityError {
+    #[error("Identity '{0}' not found")]
+    NotFound(String),
+    #[error(
+        "Identity '{name}' public key (version {version}) expired at {expires}; rotate its key"
+    )]
+    Expired {
+        name: String,
+        version: u64,
+        expires: DateTime<Utc>,
+    },
+    #[error("Identity name must not be empty")]
+    EmptyName,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureAlgorithm {
+    Ed25519,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PublicKey {
+    pub version: u64,
+    pub algorithm: SignatureAlgorithm,
+    pub expires: Option<DateTime<Utc>>,
+    pub signature: String,
+    pub key: String,
+}
+
+/// A signed identity loaded from an `identity.toml` file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Identity {
+    pub display_name: String,
+    pub email: String,
+    pub last_modified: DateTime<Utc>,
+    pub public_key: PublicKey,
+}
+
+impl Identity {
+    pub fn is_expired(&self) -> bool {
+        self.public_key
+            .expires
+            .is_some_and(|expires| expires <= Utc::now())
+    }
+}
+
+fn identity_path(config_dir: &Path, name: &str) -> PathBuf {
+    config_dir.join(IDENTITIES_DIR).join(name).join(IDENTITY_FILE)
+}
+
+/// Loads and parses the named identity, returning
+/// [`IdentityError::NotFound`] (wrapped in `EdenFsError::Other`) if no such
+/// identity exists, or [`IdentityError::Expired`] if its public key has
+/// passed its `expires` timestamp.
+pub(crate) fn load_identity(config_dir: &Path, name: &str) -> Result<Identity> {
+    if name.is_empty() {
+        return Err(EdenFsError::Other(IdentityError::EmptyName.into()));
+    }
+
+    let path = identity_path(config_dir, name);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(EdenFsError::Other(
+                IdentityError::NotFound(name.to_string()).into(),
+            ));
+        }
+        Err(e) => {
+            return Err(EdenFsError::Other(anyhow::Error::new(e).context(format!(
+                "Failed to read identity file {}",
+                path.display()
+            ))));
+        }
+    };
+
+    let identity: Identity = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse identity file {}", path.display()))
+        .map_err(EdenFsError::Other)?;
+
+    if identity.is_expired() {
+        return Err(EdenFsError::Other(
+            IdentityError::Expired {
+                name: name.to_string(),
+                version: identity.public_key.version,
+                expires: identity
+                    .public_key
+                    .expires
+                    .expect("is_expired() implies expires is Some"),
+            }
+            .into(),
+        ));
+    }
+
+    Ok(identity)
+}
+
+/// Lists the names of identities present under `config_dir/identities/`,
+/// without parsing them.
+pub(crate) fn list_identities(config_dir: &Path) -> Result<Vec<String>> {
+    let dir = config_dir.join(IDENTITIES_DIR);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(EdenFsError::Other(anyhow::Error::new(e).context(format!(
+                "Failed to read identities directory {}",
+                dir.display()
+            ))));
+        }
+    };
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| {
+            EdenFsError::Other(anyhow::Error::new(e).context("Failed to read directory entry"))
+        })?;
+        if entry.path().is_dir() {
+            if let Some(name) = entry.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Loads every identity under `config_dir/identities/`, skipping (and
+/// logging a warning for) any that fail to load -- malformed or expired --
+/// rather than failing the whole scan.
+pub(crate) fn load_all_identities(config_dir: &Path) -> Result<Vec<Identity>> {
+    let names = list_identities(config_dir)?;
+    let mut identities = Vec::with_capacity(names.len());
+    for name in names {
+        match load_identity(config_dir, &name) {
+            Ok(identity) => identities.push(identity),
+            Err(e) => tracing::warn!("Skipping identity '{}' that failed to load: {:?}", name, e),
+        }
+    }
+    Ok(identities)
+}
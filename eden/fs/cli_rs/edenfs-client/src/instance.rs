@@ -87,11 +87,17 @@
 //! ```
 
 use std::collections::BTreeMap;
+use std::collections::HashMap;
 #[cfg(windows)]
 use std::fs::remove_file;
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::SystemTime;
 
 use anyhow::anyhow;
 use anyhow::Context;
@@ -104,12 +110,15 @@ use edenfs_utils::get_executable;
 #[cfg(windows)]
 use edenfs_utils::strip_unc_prefix;
 use fbinit::expect_init;
+use lru::LruCache;
+use rand::Rng;
 use tracing::event;
 use tracing::Level;
 use util::lock::PathLock;
 
 use crate::client::EdenFsClient;
 use crate::client::StreamingEdenFsClient;
+use crate::identity::Identity;
 
 // We create a single EdenFsInstance when starting up and utilize EdenFsInstance::global()
 // whenever we need to access it.
@@ -132,6 +141,214 @@ const CONFIG_JSON: &str = "config.json";
 const CONFIG_JSON_LOCK: &str = "config.json.lock";
 const CONFIG_JSON_MODE: u32 = 0o664;
 
+/// How often `subscribe_configured_mounts` polls `config.json`'s mtime,
+/// coalescing any rewrites that happened within the window into a single
+/// re-read instead of reacting to each one.
+const CONFIG_JSON_WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Bound on the number of canonicalized paths
+/// [`EdenFsInstance::client_name_cached`] memoizes at once.
+const CLIENT_NAME_CACHE_CAPACITY: usize = 1024;
+
+/// Process-global registry of intra-process mutexes keyed by canonicalized
+/// config-lock path. `PathLock` (like most advisory file locks) only
+/// arbitrates between *processes*; two threads in the same process can still
+/// interleave a read-modify-write of `config.json` through it. Every
+/// mutation path takes the mutex for its config dir from here before
+/// attempting the file lock, closing that intra-process race window.
+static CONFIG_LOCK_REGISTRY: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = OnceLock::new();
+
+/// Returns the intra-process mutex guarding `lock_file_path`, creating one on
+/// first request. The returned `Arc` keeps the entry alive for the
+/// duration it's held; the registry entry itself is never removed, since
+/// the set of distinct config directories a process touches is small and
+/// bounded in practice.
+fn intra_process_config_lock(lock_file_path: &Path) -> Arc<Mutex<()>> {
+    let key = lock_file_path
+        .canonicalize()
+        .unwrap_or_else(|_| lock_file_path.to_path_buf());
+    let mut registry = CONFIG_LOCK_REGISTRY
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|p| p.into_inner());
+    registry
+        .entry(key)
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone()
+}
+
+/// Number of times [`EdenFsInstance::try_with_config_lock`] will detect and
+/// break a stale lock, or simply retry after a contended non-stale one, before
+/// giving up with [`EdenFsError::LockAlreadyHeld`].
+const CONFIG_LOCK_STALE_RETRIES: u32 = 5;
+
+/// Delay between retries in [`EdenFsInstance::try_with_config_lock`] when the
+/// lock is contended by a process that's still alive.
+const CONFIG_LOCK_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Identifies the process that most recently wrote the config lock file, so
+/// a contending acquisition attempt can tell "still held by a live process"
+/// apart from "abandoned by a process that crashed or was killed" — the same
+/// distinction Mercurial's `try_with_lock_no_wait` makes.
+struct LockOwner {
+    hostname: String,
+    pid: u32,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        Self {
+            hostname: sysinfo::System::host_name().unwrap_or_else(|| "unknown".to_string()),
+            pid: std::process::id(),
+        }
+    }
+
+    fn stamp(&self) -> String {
+        format!("{}:{}", self.hostname, self.pid)
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let (hostname, pid) = contents.trim().rsplit_once(':')?;
+        Some(Self {
+            hostname: hostname.to_string(),
+            pid: pid.parse().ok()?,
+        })
+    }
+
+    /// Whether this owner's process can be positively confirmed dead: it
+    /// claims to be on this host, and no such PID is currently running. A
+    /// lock claimed by another host, or one we can't disprove, is never
+    /// treated as stale.
+    fn is_dead(&self) -> bool {
+        if self.hostname != LockOwner::current().hostname {
+            return false;
+        }
+        get_executable(sysinfo::Pid::from_u32(self.pid)).is_none()
+    }
+}
+
+/// A single configured-mount change observed by
+/// [`EdenFsInstance::subscribe_configured_mounts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MountChangeEvent {
+    /// Sent once right after subscribing, with the full current mount map,
+    /// so consumers don't need a separate priming call.
+    Snapshot(BTreeMap<PathBuf, String>),
+    /// A mount was added at `path` with the given client name.
+    Added(PathBuf, String),
+    /// The mount at `path` was removed; carries the client name it had.
+    Removed(PathBuf, String),
+    /// The mount at `path` kept its path but its client name changed from
+    /// the first `String` to the second.
+    Renamed(PathBuf, String, String),
+}
+
+/// Configures the reconnect-and-retry behavior of
+/// [`EdenFsInstance::get_resilient_client`] and
+/// [`EdenFsInstance::get_resilient_streaming_client`].
+///
+/// Delays grow exponentially from `base_delay`, capped at `max_delay`, with
+/// up to `jitter_factor` of the capped delay added as random jitter so many
+/// clients backing off at once don't retry in lockstep.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub max_attempts: u32,
+    pub jitter_factor: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            max_attempts: 5,
+            jitter_factor: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let capped = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(attempt.min(16)).unwrap_or(u32::MAX))
+            .min(self.max_delay);
+        if self.jitter_factor <= 0.0 {
+            return capped;
+        }
+        let jitter = capped.mul_f64(self.jitter_factor * rand::thread_rng().gen::<f64>());
+        capped + jitter
+    }
+
+    /// Whether `error` looks like a transient connection failure (the socket
+    /// doesn't exist yet, or IO errors in the reset/broken-pipe/connection-
+    /// refused family) worth retrying, as opposed to an application-level
+    /// error that should surface immediately.
+    fn is_retryable(&self, error: &anyhow::Error) -> bool {
+        if let Some(io_err) = error.downcast_ref::<std::io::Error>() {
+            return Self::is_retryable_io_error_kind(io_err.kind());
+        }
+        // Raised by an actual Thrift connection attempt (see `retry_connect`
+        // below), as opposed to `get_socket_path` merely checking the socket
+        // file exists.
+        if let Some(eden_err) = error.downcast_ref::<EdenFsError>() {
+            return match eden_err {
+                EdenFsError::ThriftConnectionTimeout(_) => true,
+                EdenFsError::ThriftIoError(io_err) => Self::is_retryable_io_error_kind(io_err.kind()),
+                _ => false,
+            };
+        }
+        // `get_socket_path(true)` reports a missing socket as a plain
+        // `anyhow!` rather than an `io::Error`; treat it the same way since
+        // it's the common "daemon hasn't (re)created its socket yet" case.
+        error.to_string().contains("doesn't exist on this machine")
+    }
+
+    fn is_retryable_io_error_kind(kind: std::io::ErrorKind) -> bool {
+        matches!(
+            kind,
+            std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionRefused
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::NotFound
+        )
+    }
+}
+
+/// Drives `connect` (one attempt at producing a connected client) under
+/// `policy`'s backoff schedule: retries while `policy.is_retryable` accepts
+/// the error and attempts remain, sleeping `policy.delay_for_attempt`
+/// between them, and surfaces the last error once `max_attempts` is
+/// exhausted or the error isn't retryable. Shared by `get_resilient_client`
+/// and `get_resilient_streaming_client` so both actually validate the
+/// connection `connect` establishes, not just that a socket file exists.
+async fn retry_connect<T, F, Fut>(policy: &RetryPolicy, mut connect: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = std::result::Result<T, anyhow::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        match connect().await {
+            Ok(client) => return Ok(client),
+            Err(e) if attempt + 1 < policy.max_attempts && policy.is_retryable(&e) => {
+                attempt += 1;
+                tracing::warn!(
+                    "Resilient client connect attempt {}/{} failed, retrying: {:?}",
+                    attempt,
+                    policy.max_attempts,
+                    e
+                );
+                tokio::time::sleep(policy.delay_for_attempt(attempt)).await;
+            }
+            Err(e) => return Err(EdenFsError::Other(e)),
+        }
+    }
+}
+
 /// Manages daemon-related (EdenFS) resources besides the Thrift connection.
 ///
 /// `EdenFsInstance` provides access to configuration, socket paths, client directories,
@@ -143,11 +360,94 @@ const CONFIG_JSON_MODE: u32 = 0o664;
 /// * `config_dir` - Path to the EdenFS configuration directory
 /// * `etc_eden_dir` - Path to the system-wide EdenFS configuration directory
 /// * `home_dir` - Optional path to the user's home directory
-#[derive(Debug)]
 pub struct EdenFsInstance {
     config_dir: PathBuf,
     etc_eden_dir: PathBuf,
     home_dir: Option<PathBuf>,
+    client_name_cache: Mutex<ClientNameCache>,
+    checkout_config: CheckoutConfig,
+}
+
+impl std::fmt::Debug for EdenFsInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EdenFsInstance")
+            .field("config_dir", &self.config_dir)
+            .field("etc_eden_dir", &self.etc_eden_dir)
+            .field("home_dir", &self.home_dir)
+            .finish()
+    }
+}
+
+/// Bounded memoization of [`EdenFsInstance::client_name_cached`], keyed by
+/// canonicalized path. Invalidated wholesale whenever `config.json`'s mtime
+/// changes, since that's the only thing that can make a cached resolution
+/// stale (a mount being added, removed, or renamed).
+struct ClientNameCache {
+    entries: LruCache<PathBuf, String>,
+    config_mtime: Option<SystemTime>,
+}
+
+/// In-process cache of the parsed `config.json` directory map used by
+/// [`EdenFsInstance::with_directory_map_mut`], analogous to Deno's wrapper
+/// around `deno_lockfile::Lockfile`: holds the last map read (and the
+/// `config.json` mtime it was read at) so a mutation under an already-held
+/// config lock doesn't always have to re-read and re-parse the file from
+/// disk, while still re-reading whenever an external mtime change proves the
+/// cached copy stale.
+#[derive(Default)]
+struct CheckoutConfig {
+    state: Mutex<CheckoutConfigState>,
+}
+
+#[derive(Default)]
+struct CheckoutConfigState {
+    map: Option<BTreeMap<PathBuf, String>>,
+    mtime: Option<SystemTime>,
+}
+
+impl CheckoutConfig {
+    /// Returns the directory map, reusing the cached copy if `config_file_path`'s
+    /// mtime matches the one it was cached at, and re-reading from disk
+    /// otherwise.
+    fn cached_map(
+        &self,
+        config_dir: &Path,
+        config_file_path: &Path,
+    ) -> Result<BTreeMap<PathBuf, String>> {
+        let current_mtime = std::fs::metadata(config_file_path)
+            .and_then(|m| m.modified())
+            .ok();
+        let state = self.state.lock().unwrap_or_else(|p| p.into_inner());
+        if state.mtime == current_mtime {
+            if let Some(map) = &state.map {
+                return Ok(map.clone());
+            }
+        }
+        drop(state);
+        read_configured_mounts_map(config_dir).map_err(EdenFsError::Other)
+    }
+
+    /// Caches `map` as current, stamped with `config_file_path`'s mtime as of
+    /// now (i.e. after any write the caller just performed).
+    fn store(&self, config_file_path: &Path, map: BTreeMap<PathBuf, String>) {
+        let mtime = std::fs::metadata(config_file_path)
+            .and_then(|m| m.modified())
+            .ok();
+        let mut state = self.state.lock().unwrap_or_else(|p| p.into_inner());
+        state.map = Some(map);
+        state.mtime = mtime;
+    }
+}
+
+impl Default for ClientNameCache {
+    fn default() -> Self {
+        Self {
+            entries: LruCache::new(
+                NonZeroUsize::new(CLIENT_NAME_CACHE_CAPACITY).expect("capacity is non-zero"),
+            ),
+            config_mtime: None,
+        }
+    }
 }
 
 impl EdenFsInstance {
@@ -216,6 +516,8 @@ impl EdenFsInstance {
             config_dir,
             etc_eden_dir,
             home_dir,
+            client_name_cache: Mutex::new(ClientNameCache::default()),
+            checkout_config: CheckoutConfig::default(),
         }
     }
 
@@ -332,6 +634,33 @@ impl EdenFsInstance {
         self.home_dir.as_ref()
     }
 
+    /// Loads and parses the named identity from
+    /// `config_dir/identities/<name>/identity.toml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EdenFsError::Other` wrapping [`crate::identity::IdentityError::NotFound`]
+    /// if no such identity exists, or
+    /// [`crate::identity::IdentityError::Expired`] if its public key's
+    /// `expires` timestamp has passed, so callers can distinguish the two
+    /// and prompt for a key rotation only in the latter case.
+    pub fn load_identity(&self, name: &str) -> Result<Identity> {
+        crate::identity::load_identity(&self.config_dir, name)
+    }
+
+    /// Lists the names of identities present under
+    /// `config_dir/identities/`, without parsing them.
+    pub fn list_identities(&self) -> Result<Vec<String>> {
+        crate::identity::list_identities(&self.config_dir)
+    }
+
+    /// Loads every identity under `config_dir/identities/`, skipping (and
+    /// logging a warning for) any entry that fails to load -- malformed or
+    /// expired -- rather than failing the whole scan.
+    pub fn load_all_identities(&self) -> Result<Vec<Identity>> {
+        crate::identity::load_all_identities(&self.config_dir)
+    }
+
     /// Creates and returns a new `EdenFsClient` for interacting with EdenFS.
     ///
     /// This method creates a new client that connects to the EdenFS daemon using the
@@ -357,6 +686,57 @@ impl EdenFsInstance {
         StreamingEdenFsClient::new(expect_init(), self.socketfile(), None)
     }
 
+    /// Creates and returns an `EdenFsClient` resilient to daemon restarts and
+    /// transient socket hiccups, per `policy` (or `RetryPolicy::default()` if
+    /// `None`).
+    ///
+    /// Unlike [`get_client`](Self::get_client), each attempt re-resolves
+    /// [`socketfile`](Self::socketfile) and exchanges a real Thrift call
+    /// (`get_health`) with the daemon before handing the client back, so a
+    /// daemon that rewrote its socket path (e.g. after a restart) or isn't
+    /// actually answering yet is retried rather than handed to the caller
+    /// as if it were live. Only connection-reset / broken-pipe class errors
+    /// are retried with exponential backoff and jitter; any other error
+    /// (including application-level Thrift errors) surfaces immediately.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing a connected `EdenFsClient`, or the last
+    /// error encountered once `policy.max_attempts` is exhausted.
+    pub async fn get_resilient_client(&self, policy: Option<RetryPolicy>) -> Result<EdenFsClient> {
+        let policy = policy.unwrap_or_default();
+        retry_connect(&policy, || async {
+            let socket = self.get_socket_path(true)?;
+            let client = EdenFsClient::new(expect_init(), socket, None);
+            client.get_health().await.map_err(anyhow::Error::new)?;
+            Ok(client)
+        })
+        .await
+    }
+
+    /// Streaming equivalent of [`get_resilient_client`](Self::get_resilient_client).
+    ///
+    /// `StreamingEdenFsClient` has no health-check RPC of its own in this
+    /// crate, but it talks to the same daemon over the same socket as
+    /// `EdenFsClient`, so each attempt validates the connection with a
+    /// throwaway plain client's `get_health` before handing back a streaming
+    /// client pointed at the same (now-confirmed-live) socket.
+    pub async fn get_resilient_streaming_client(
+        &self,
+        policy: Option<RetryPolicy>,
+    ) -> Result<StreamingEdenFsClient> {
+        let policy = policy.unwrap_or_default();
+        retry_connect(&policy, || async {
+            let socket = self.get_socket_path(true)?;
+            EdenFsClient::new(expect_init(), socket.clone(), None)
+                .get_health()
+                .await
+                .map_err(anyhow::Error::new)?;
+            Ok(StreamingEdenFsClient::new(expect_init(), socket, None))
+        })
+        .await
+    }
+
     /// Returns the path to the EdenFS socket file.
     ///
     /// # Returns
@@ -575,20 +955,110 @@ impl EdenFsInstance {
     /// }
     /// ```
     pub fn get_configured_mounts_map(&self) -> Result<BTreeMap<PathBuf, String>, anyhow::Error> {
-        let directory_map = self.config_dir.join(CONFIG_JSON);
-        match std::fs::read_to_string(&directory_map) {
-            Ok(buff) => {
-                let string_map = serde_json::from_str::<BTreeMap<String, String>>(&buff)
-                    .with_context(|| format!("Failed to parse directory map: {:?}", &buff))?;
-                Ok(string_map
-                    .into_iter()
-                    .map(|(key, val)| (key.into(), val))
-                    .collect())
+        read_configured_mounts_map_locked(&self.config_dir)
+    }
+
+    /// Subscribes to changes in the set of configured mounts, returning a
+    /// `Receiver` of add/remove/rename events.
+    ///
+    /// An initial [`MountChangeEvent::Snapshot`] of the current mount map is
+    /// sent immediately, so consumers don't need a separate priming call.
+    /// After that, `config_dir/config.json` is polled every `debounce`
+    /// (coalescing rapid rewrites into a single re-read), each re-read
+    /// happens atomically under `CONFIG_JSON_LOCK`, and only the deltas
+    /// against the previously observed map are sent.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `tokio::sync::mpsc::Receiver<MountChangeEvent>`. Dropping
+    /// it stops the background watcher task.
+    pub fn subscribe_configured_mounts(&self) -> tokio::sync::mpsc::Receiver<MountChangeEvent> {
+        self.subscribe_configured_mounts_with_debounce(CONFIG_JSON_WATCH_DEBOUNCE)
+    }
+
+    fn subscribe_configured_mounts_with_debounce(
+        &self,
+        debounce: Duration,
+    ) -> tokio::sync::mpsc::Receiver<MountChangeEvent> {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let config_dir = self.config_dir.clone();
+
+        tokio::spawn(async move {
+            let config_file_path = config_dir.join(CONFIG_JSON);
+
+            let mut previous = match read_configured_mounts_map_locked(&config_dir) {
+                Ok(map) => map,
+                Err(e) => {
+                    tracing::warn!("Failed to read initial configured mounts map: {:?}", e);
+                    BTreeMap::new()
+                }
+            };
+            if tx
+                .send(MountChangeEvent::Snapshot(previous.clone()))
+                .await
+                .is_err()
+            {
+                return;
             }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
-            Err(e) => Err(e)
-                .with_context(|| format!("Failed to read directory map from {:?}", directory_map)),
-        }
+
+            let mut last_seen_mtime = std::fs::metadata(&config_file_path)
+                .and_then(|m| m.modified())
+                .ok();
+            let mut interval = tokio::time::interval(debounce);
+            loop {
+                interval.tick().await;
+                if tx.is_closed() {
+                    return;
+                }
+
+                let mtime = std::fs::metadata(&config_file_path)
+                    .and_then(|m| m.modified())
+                    .ok();
+                if mtime == last_seen_mtime {
+                    continue;
+                }
+                last_seen_mtime = mtime;
+
+                let current = match read_configured_mounts_map_locked(&config_dir) {
+                    Ok(map) => map,
+                    Err(e) => {
+                        tracing::warn!("Failed to re-read configured mounts map: {:?}", e);
+                        continue;
+                    }
+                };
+
+                for (path, name) in &current {
+                    let event = match previous.get(path) {
+                        None => Some(MountChangeEvent::Added(path.clone(), name.clone())),
+                        Some(old_name) if old_name != name => Some(MountChangeEvent::Renamed(
+                            path.clone(),
+                            old_name.clone(),
+                            name.clone(),
+                        )),
+                        _ => None,
+                    };
+                    if let Some(event) = event {
+                        if tx.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                for (path, name) in &previous {
+                    if !current.contains_key(path)
+                        && tx
+                            .send(MountChangeEvent::Removed(path.clone(), name.clone()))
+                            .await
+                            .is_err()
+                    {
+                        return;
+                    }
+                }
+
+                previous = current;
+            }
+        });
+
+        rx
     }
 
     /// Returns the path to the EdenFS clients directory.
@@ -736,6 +1206,56 @@ impl EdenFsInstance {
         }
     }
 
+    /// Like [`client_name`](Self::client_name), but consults a bounded LRU
+    /// cache keyed by canonicalized path first, so hot paths resolving many
+    /// paths to their owning checkout don't re-read and re-parse
+    /// `config.json` on every call.
+    ///
+    /// The cache is invalidated wholesale whenever `config.json`'s mtime
+    /// changes (e.g. because a mount was added or removed), so a stale
+    /// mapping is never returned for longer than it takes the file to
+    /// change. Call [`clear_client_name_cache`](Self::clear_client_name_cache)
+    /// directly if a caller knows state changed through some other means
+    /// (e.g. the [`subscribe_configured_mounts`](Self::subscribe_configured_mounts)
+    /// stream).
+    pub fn client_name_cached(&self, path: &Path) -> Result<String> {
+        let path = path.canonicalize().from_err()?;
+        #[cfg(windows)]
+        let path = strip_unc_prefix(path);
+
+        let config_mtime = std::fs::metadata(self.config_dir.join(CONFIG_JSON))
+            .and_then(|m| m.modified())
+            .ok();
+
+        {
+            let mut cache = self.lock_client_name_cache();
+            if cache.config_mtime != config_mtime {
+                cache.entries.clear();
+                cache.config_mtime = config_mtime;
+            }
+            if let Some(name) = cache.entries.get(&path) {
+                return Ok(name.clone());
+            }
+        }
+
+        let name = self.client_name(&path)?;
+        self.lock_client_name_cache().entries.put(path, name.clone());
+        Ok(name)
+    }
+
+    /// Drops every memoized path -> client-name mapping.
+    pub fn clear_client_name_cache(&self) {
+        let mut cache = self.lock_client_name_cache();
+        cache.entries.clear();
+        cache.config_mtime = None;
+    }
+
+    fn lock_client_name_cache(&self) -> std::sync::MutexGuard<'_, ClientNameCache> {
+        self.client_name_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
     /// Returns the configuration directory for a specific client.
     ///
     /// # Parameters
@@ -849,51 +1369,506 @@ impl EdenFsInstance {
     /// }
     /// ```
     pub fn remove_path_from_directory_map(&self, path: &Path) -> Result<()> {
-        let lock_file_path = self.config_dir.join(CONFIG_JSON_LOCK);
-        let config_file_path = self.config_dir.join(CONFIG_JSON);
-
-        // For Linux and MacOS we have a lock file "config.json.lock" under the config directory
-        // which works as a file lock to prevent the file "config.json" being accessed by
-        // multiple processes at the same time.
-        //
-        // In Python CLI code, FileLock lib is used to create config.json.lock.
-        // In Rust, we use PathLock from "scm/lib/util"
-        let _lock = PathLock::exclusive(&lock_file_path).with_context(|| {
-            format!("Failed to open the lock file {}", lock_file_path.display())
-        })?;
-
-        // Lock acquired, now we can read and write to the "config.json" file
-
         // On Windows the "Path" crate will append the prefix "\\?\" to the original path when
         // "canonicalize()" is called to indicate the path is in unicode.
         // We need to strip the prefix before checking the key in "config.json" file
         // For non-windows platforms, this is no-op.
-        let entry_key = dunce::simplified(path);
-        let mut all_checkout_map = self.get_configured_mounts_map()?;
-        let original_num_of_entries = all_checkout_map.len();
-
-        all_checkout_map.retain(|path, _| dunce::simplified(path) != entry_key);
-
-        if all_checkout_map.len() < original_num_of_entries {
-            atomic_write(&config_file_path, CONFIG_JSON_MODE, true, |f| {
-                serde_json::to_writer_pretty(f, &all_checkout_map)?;
-                Ok(())
-            })
-            .with_context(|| {
-                format!(
-                    "Failed to write updated config JSON back to {}",
-                    config_file_path.display()
-                )
-            })?;
-        } else {
-            event!(
-                Level::WARN,
-                "There is not entry for {} in config.json",
-                path.display()
+        let entry_key = dunce::simplified(path).to_path_buf();
+        self.with_directory_map_mut(|map| {
+            let original_num_of_entries = map.len();
+            map.retain(|path, _| dunce::simplified(path) != entry_key);
+            if map.len() < original_num_of_entries {
+                Ok((true, ()))
+            } else {
+                event!(
+                    Level::WARN,
+                    "There is not entry for {} in config.json",
+                    path.display()
+                );
+                Ok((false, ()))
+            }
+        })
+    }
+
+    /// Adds (or overwrites) an entry in the EdenFS directory map, mapping
+    /// `path` to `client_name`. The symmetric counterpart to
+    /// [`remove_path_from_directory_map`](Self::remove_path_from_directory_map).
+    pub fn add_path_to_directory_map(&self, path: &Path, client_name: &str) -> Result<()> {
+        let entry_key = dunce::simplified(path).to_path_buf();
+        let client_name = client_name.to_string();
+        self.with_directory_map_mut(|map| {
+            map.insert(entry_key.clone(), client_name.clone());
+            Ok((true, ()))
+        })
+    }
+
+    /// Transactional entry point for mutating the `config.json` directory
+    /// map: acquires the config lock via
+    /// [`try_with_config_lock`](Self::try_with_config_lock) (so a crashed
+    /// lock holder is detected and broken rather than blocking forever),
+    /// hands the current map to `mutate`, and -- only if `mutate` reports
+    /// the map changed -- atomically writes it back with
+    /// [`atomic_write_with_retries`]. Composes multiple edits under a single
+    /// lock acquisition and avoids the redundant disk reads every prior
+    /// accessor did independently.
+    pub fn with_directory_map_mut<R>(
+        &self,
+        mutate: impl FnOnce(&mut BTreeMap<PathBuf, String>) -> Result<(bool, R)>,
+    ) -> Result<R> {
+        let config_file_path = self.config_dir.join(CONFIG_JSON);
+        self.try_with_config_lock(|| {
+            let mut map = self
+                .checkout_config
+                .cached_map(&self.config_dir, &config_file_path)?;
+
+            let (changed, result) = mutate(&mut map)?;
+
+            if changed {
+                atomic_write_with_retries(&config_file_path, CONFIG_JSON_MODE, true, |f| {
+                    serde_json::to_writer_pretty(f, &map)?;
+                    Ok(())
+                })
+                .with_context(|| {
+                    format!(
+                        "Failed to write updated config JSON back to {}",
+                        config_file_path.display()
+                    )
+                })?;
+            }
+
+            self.checkout_config.store(&config_file_path, map);
+            Ok(result)
+        })
+    }
+
+    /// Runs `f` while holding the `config.json` lock, without blocking
+    /// indefinitely if another (possibly dead) process holds it.
+    ///
+    /// Like Mercurial's `try_with_lock_no_wait`: each successful acquisition
+    /// stamps the lock file with `<hostname>:<pid>` identifying the holder.
+    /// On contention, that stamp is read back; if it names a process on this
+    /// host that's no longer running, the lock is considered abandoned and
+    /// removed so acquisition can be retried immediately, otherwise the
+    /// attempt is simply retried after a short delay. Either way, acquisition
+    /// is retried up to [`CONFIG_LOCK_STALE_RETRIES`] times before giving up
+    /// with [`EdenFsError::LockAlreadyHeld`], which callers can distinguish
+    /// from a generic I/O error to fail fast instead of hanging on a crashed
+    /// EdenFS-adjacent process.
+    ///
+    /// Also serializes against other threads in this same process via
+    /// [`intra_process_config_lock`], since the file lock above only
+    /// arbitrates between processes.
+    pub fn try_with_config_lock<R>(&self, f: impl FnOnce() -> Result<R>) -> Result<R> {
+        let lock_file_path = self.config_dir.join(CONFIG_JSON_LOCK);
+
+        // Serialize against other threads in this process before even
+        // attempting the (inter-process) file lock, since the file lock
+        // alone can't arbitrate between threads sharing one process.
+        let intra_process_lock = intra_process_config_lock(&lock_file_path);
+        let _intra_process_guard = intra_process_lock.lock().unwrap_or_else(|p| p.into_inner());
+
+        for attempt in 0..=CONFIG_LOCK_STALE_RETRIES {
+            match PathLock::try_exclusive(&lock_file_path) {
+                Ok(lock) => {
+                    let _ = std::fs::write(&lock_file_path, LockOwner::current().stamp());
+                    let result = f();
+                    drop(lock);
+                    return result;
+                }
+                Err(e) if attempt < CONFIG_LOCK_STALE_RETRIES => {
+                    let owner = std::fs::read_to_string(&lock_file_path)
+                        .ok()
+                        .and_then(|contents| LockOwner::parse(&contents));
+                    match owner {
+                        Some(owner) if owner.is_dead() => {
+                            tracing::warn!(
+                                "Breaking stale config lock {} held by dead process {}",
+                                lock_file_path.display(),
+                                owner.stamp(),
+                            );
+                            let _ = std::fs::remove_file(&lock_file_path);
+                        }
+                        _ => {
+                            tracing::debug!(
+                                "Config lock {} contended ({:?}), retrying ({}/{})",
+                                lock_file_path.display(),
+                                e,
+                                attempt + 1,
+                                CONFIG_LOCK_STALE_RETRIES
+                            );
+                            std::thread::sleep(CONFIG_LOCK_RETRY_DELAY);
+                        }
+                    }
+                }
+                Err(_) => return Err(EdenFsError::LockAlreadyHeld(lock_file_path)),
+            }
+        }
+        unreachable!("the loop above always returns on its final iteration")
+    }
+}
+
+/// Maximum number of attempts [`atomic_write_with_retries`] makes before
+/// surfacing the last error.
+const ATOMIC_WRITE_MAX_ATTEMPTS: u32 = 7;
+
+/// Starting (and, doubled each attempt, capped) backoff used by
+/// [`atomic_write_with_retries`].
+const ATOMIC_WRITE_BASE_DELAY: Duration = Duration::from_millis(10);
+const ATOMIC_WRITE_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// Like [`atomic_write`], but retries the whole temp-file-write-then-rename
+/// sequence with exponential backoff when it fails with an error that looks
+/// transient — antivirus or another process briefly holding the temp file
+/// open, a Windows sharing violation, an interrupted syscall — instead of
+/// failing permanently on the first hiccup. Modeled on Deno's
+/// `atomic_write_file_with_retries`.
+fn atomic_write_with_retries(
+    path: &Path,
+    mode: u32,
+    fsync: bool,
+    write: impl Fn(&mut std::fs::File) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let mut attempt = 0;
+    loop {
+        match atomic_write(path, mode, fsync, &write) {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt + 1 < ATOMIC_WRITE_MAX_ATTEMPTS && is_retryable_write_error(&e) => {
+                let delay = ATOMIC_WRITE_BASE_DELAY
+                    .saturating_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+                    .min(ATOMIC_WRITE_MAX_DELAY);
+                tracing::debug!(
+                    "atomic_write to {} failed ({:?}), retrying ({}/{}) in {:?}",
+                    path.display(),
+                    e,
+                    attempt + 1,
+                    ATOMIC_WRITE_MAX_ATTEMPTS,
+                    delay
+                );
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Whether `e` looks like a transient failure of the write step inside
+/// [`atomic_write`] (temp-file write or rename), worth retrying rather than
+/// surfacing immediately.
+fn is_retryable_write_error(e: &std::io::Error) -> bool {
+    if matches!(
+        e.kind(),
+        std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::Interrupted
+    ) {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        // ETXTBSY: "Text file busy" -- observed when another process has the
+        // temp file open for execution/mapping during the rename.
+        const ETXTBSY: i32 = 26;
+        if e.raw_os_error() == Some(ETXTBSY) {
+            return true;
+        }
+    }
+    #[cfg(windows)]
+    {
+        // ERROR_SHARING_VIOLATION: another process (commonly antivirus) has
+        // the file open without the sharing flags we need.
+        const ERROR_SHARING_VIOLATION: i32 = 32;
+        if e.raw_os_error() == Some(ERROR_SHARING_VIOLATION) {
+            return true;
+        }
+    }
+    false
+}
+
+fn read_configured_mounts_map(config_dir: &Path) -> Result<BTreeMap<PathBuf, String>, anyhow::Error> {
+    let directory_map = config_dir.join(CONFIG_JSON);
+    match std::fs::read_to_string(&directory_map) {
+        Ok(buff) => {
+            let string_map = serde_json::from_str::<BTreeMap<String, String>>(&buff)
+                .with_context(|| format!("Failed to parse directory map: {:?}", &buff))?;
+            Ok(string_map
+                .into_iter()
+                .map(|(key, val)| (key.into(), val))
+                .collect())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => {
+            Err(e).with_context(|| format!("Failed to read directory map from {:?}", directory_map))
+        }
+    }
+}
+
+/// Reads `config.json` while holding `CONFIG_JSON_LOCK`, so the read never
+/// observes a half-written file from a concurrent
+/// `EdenFsInstance::remove_path_from_directory_map` (or similar) call.
+///
+/// `config_dir` may be read-only (e.g. a snapshot or a read-only bind mount):
+/// if acquiring the lock fails because the directory can't be written to,
+/// locking is skipped entirely and `config.json` is read unlocked, on the
+/// assumption that a read-only directory can't have a writer racing us
+/// anyway. Any other lock failure (e.g. genuine contention) is still an
+/// error, so this never silently reads through an in-progress write on a
+/// writable directory.
+fn read_configured_mounts_map_locked(
+    config_dir: &Path,
+) -> Result<BTreeMap<PathBuf, String>, anyhow::Error> {
+    let lock_file_path = config_dir.join(CONFIG_JSON_LOCK);
+    match PathLock::exclusive(&lock_file_path) {
+        Ok(_lock) => read_configured_mounts_map(config_dir),
+        Err(e) if looks_like_readonly_fs(&e) => {
+            tracing::debug!(
+                "Config directory {} appears read-only ({:?}); reading config.json unlocked",
+                config_dir.display(),
+                e
             );
+            read_configured_mounts_map(config_dir)
         }
+        Err(e) => Err(e)
+            .with_context(|| format!("Failed to open the lock file {}", lock_file_path.display())),
+    }
+}
+
+/// Whether `e` (from attempting to open/create the `config.json.lock` file)
+/// looks like the underlying filesystem is mounted read-only, as opposed to
+/// e.g. genuine contention from another process holding the lock.
+fn looks_like_readonly_fs(e: &std::io::Error) -> bool {
+    if e.kind() == std::io::ErrorKind::PermissionDenied {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        // EROFS: "Read-only file system". `std::io::ErrorKind` has no
+        // portable variant for this yet.
+        const EROFS: i32 = 30;
+        if e.raw_os_error() == Some(EROFS) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    /// Spawns and waits on a short-lived child process, returning its pid.
+    /// By the time this returns, the pid is guaranteed to no longer belong
+    /// to any running process.
+    fn dead_pid() -> u32 {
+        let child = Command::new("true").spawn().expect("failed to spawn `true`");
+        let pid = child.id();
+        Command::new("true")
+            .spawn()
+            .and_then(|mut c| c.wait())
+            .ok();
+        // Reap the original child so its pid isn't left as a zombie (which
+        // `get_executable` might still report as "running" on some platforms).
+        let mut child = child;
+        let _ = child.wait();
+        pid
+    }
+
+    #[test]
+    fn lock_owner_stamp_roundtrips_through_parse() {
+        let owner = LockOwner::current();
+        let parsed = LockOwner::parse(&owner.stamp()).expect("stamp should parse");
+        assert_eq!(parsed.hostname, owner.hostname);
+        assert_eq!(parsed.pid, owner.pid);
+    }
+
+    #[test]
+    fn lock_owner_is_dead_for_exited_process_on_this_host() {
+        let owner = LockOwner {
+            hostname: LockOwner::current().hostname,
+            pid: dead_pid(),
+        };
+        assert!(owner.is_dead());
+    }
+
+    #[test]
+    fn lock_owner_is_not_dead_for_live_process_on_this_host() {
+        let owner = LockOwner {
+            hostname: LockOwner::current().hostname,
+            pid: std::process::id(),
+        };
+        assert!(!owner.is_dead());
+    }
+
+    #[test]
+    fn lock_owner_is_not_dead_when_claimed_by_another_host() {
+        // Even a pid we know is dead on *this* host must not be treated as
+        // stale if the stamp claims a different hostname -- we have no way
+        // to check liveness on a host that isn't ours.
+        let owner = LockOwner {
+            hostname: format!("{}-elsewhere", LockOwner::current().hostname),
+            pid: dead_pid(),
+        };
+        assert!(!owner.is_dead());
+    }
+
+    #[test]
+    fn try_with_config_lock_breaks_lock_held_by_dead_process() {
+        let config_dir = tempfile::tempdir().expect("tempdir");
+        let instance = EdenFsInstance::new(config_dir.path().to_path_buf(), PathBuf::new(), None);
+
+        let stale_owner = LockOwner {
+            hostname: LockOwner::current().hostname,
+            pid: dead_pid(),
+        };
+        std::fs::write(
+            config_dir.path().join(CONFIG_JSON_LOCK),
+            stale_owner.stamp(),
+        )
+        .expect("failed to seed stale lock file");
+
+        let result = instance.try_with_config_lock(|| Ok(42));
+        assert_eq!(result.expect("stale lock should have been broken"), 42);
+
+        // The lock file should now be stamped with this process as the owner.
+        let contents = std::fs::read_to_string(config_dir.path().join(CONFIG_JSON_LOCK))
+            .expect("lock file should still exist after being broken and reacquired");
+        let new_owner = LockOwner::parse(&contents).expect("stamp should parse");
+        assert_eq!(new_owner.pid, std::process::id());
+    }
+
+    #[test]
+    fn try_with_config_lock_succeeds_on_fresh_config_dir() {
+        let config_dir = tempfile::tempdir().expect("tempdir");
+        let instance = EdenFsInstance::new(config_dir.path().to_path_buf(), PathBuf::new(), None);
+
+        let result = instance.try_with_config_lock(|| Ok("done".to_string()));
+        assert_eq!(result.expect("lock should be acquired"), "done");
+    }
+
+    #[test]
+    fn is_retryable_write_error_classifies_transient_errors() {
+        assert!(is_retryable_write_error(&std::io::Error::from(
+            std::io::ErrorKind::PermissionDenied
+        )));
+        assert!(is_retryable_write_error(&std::io::Error::from(
+            std::io::ErrorKind::Interrupted
+        )));
+        assert!(!is_retryable_write_error(&std::io::Error::from(
+            std::io::ErrorKind::NotFound
+        )));
+        assert!(!is_retryable_write_error(&std::io::Error::other(
+            "some permanent failure"
+        )));
+    }
+
+    #[test]
+    fn atomic_write_with_retries_surfaces_non_retryable_error_immediately() {
+        // The parent directory doesn't exist, so the underlying write fails
+        // with `NotFound`, which `is_retryable_write_error` doesn't classify
+        // as transient -- this should fail on the first attempt rather than
+        // retrying `ATOMIC_WRITE_MAX_ATTEMPTS` times.
+        let missing_dir = tempfile::tempdir()
+            .expect("tempdir")
+            .path()
+            .join("does-not-exist")
+            .join("file.txt");
+
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let result = atomic_write_with_retries(&missing_dir, 0o644, false, |file| {
+            attempts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            std::io::Write::write_all(file, b"hello")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn retry_policy_is_retryable_recognizes_thrift_errors() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable(&anyhow::Error::new(EdenFsError::ThriftIoError(
+            std::io::Error::from(std::io::ErrorKind::ConnectionReset)
+        ))));
+        assert!(policy.is_retryable(&anyhow::Error::new(EdenFsError::ThriftConnectionTimeout(
+            PathBuf::from("/path/to/socket")
+        ))));
+        assert!(!policy.is_retryable(&anyhow::Error::new(EdenFsError::ThriftIoError(
+            std::io::Error::from(std::io::ErrorKind::PermissionDenied)
+        ))));
+        assert!(!policy.is_retryable(&anyhow::Error::new(EdenFsError::UnknownMethod(
+            "getHealth".to_string()
+        ))));
+    }
+
+    #[tokio::test]
+    async fn retry_connect_retries_transient_errors_then_succeeds() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            max_attempts: 5,
+            jitter_factor: 0.0,
+        };
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+
+        let result = retry_connect(&policy, || {
+            let attempts = &attempts;
+            async move {
+                if attempts.fetch_add(1, Ordering::Relaxed) < 2 {
+                    Err(anyhow::Error::new(std::io::Error::from(
+                        std::io::ErrorKind::ConnectionReset,
+                    )))
+                } else {
+                    Ok(42)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.expect("should eventually succeed"), 42);
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn retry_connect_surfaces_non_retryable_error_immediately() {
+        let policy = RetryPolicy::default();
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+
+        let result: Result<()> = retry_connect(&policy, || {
+            let attempts = &attempts;
+            async move {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(anyhow::anyhow!("permission denied"))
+            }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_connect_gives_up_after_max_attempts() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            max_attempts: 3,
+            jitter_factor: 0.0,
+        };
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+
+        let result: Result<()> = retry_connect(&policy, || {
+            let attempts = &attempts;
+            async move {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(anyhow::Error::new(std::io::Error::from(
+                    std::io::ErrorKind::ConnectionReset,
+                )))
+            }
+        })
+        .await;
 
-        // Lock will be released when _lock is dropped
-        Ok(())
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
     }
 }
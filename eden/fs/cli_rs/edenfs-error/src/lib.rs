@@ -67,6 +67,9 @@ pub enum EdenFsError {
     #[error("The running version of the EdenFS daemon doesn't know that method.")]
     UnknownMethod(String),
 
+    #[error("Lock file {0:?} is held by another live process")]
+    LockAlreadyHeld(PathBuf),
+
     #[error("{0}")]
     Other(#[from] anyhow::Error),
 }
@@ -111,6 +114,22 @@ macro_rules! impl_has_error_handling_strategy {
     };
 }
 
+impl HasErrorHandlingStrategy for EdenFsError {
+    fn get_error_handling_strategy(&self) -> ErrorHandlingStrategy {
+        match self {
+            EdenFsError::ThriftConnectionTimeout(_)
+            | EdenFsError::RequestTimeout(_)
+            | EdenFsError::ThriftIoError(_) => ErrorHandlingStrategy::Reconnect,
+            EdenFsError::ConfigurationError(_)
+            | EdenFsError::UnknownMethod(_)
+            | EdenFsError::LockAlreadyHeld(_) => ErrorHandlingStrategy::Abort,
+            // We can't introspect an opaque `anyhow::Error`, so don't guess
+            // at retrying it.
+            EdenFsError::Other(_) => ErrorHandlingStrategy::Abort,
+        }
+    }
+}
+
 impl_has_error_handling_strategy!(AddBindMountError);
 impl_has_error_handling_strategy!(ChangesSinceV2Error);
 impl_has_error_handling_strategy!(ClearAndCompactLocalStoreError);
@@ -144,3 +163,84 @@ impl_has_error_handling_strategy!(UnmountV2Error);
 
 // TODO: Add error handling strategy for streaming endpoints
 //impl_has_error_handling_strategy!(StreamJournalChangedError);
+
+/// Configures [`retry_with_strategy`]'s backoff: delays grow exponentially
+/// from `base_delay`, capped at `max_delay`. The actual sleep is "full
+/// jitter" -- a uniform random duration in `[0, capped]` -- so many EdenFS
+/// clients hitting the same failure don't retry in lockstep.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryStrategyPolicy {
+    pub base_delay: std::time::Duration,
+    pub max_delay: std::time::Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for RetryStrategyPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: std::time::Duration::from_millis(50),
+            max_delay: std::time::Duration::from_secs(10),
+            max_attempts: 5,
+        }
+    }
+}
+
+impl RetryStrategyPolicy {
+    fn full_jitter_delay(&self, attempt: u32) -> std::time::Duration {
+        let capped = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(attempt.min(16)).unwrap_or(u32::MAX))
+            .min(self.max_delay);
+        capped.mul_f64(rand::Rng::gen::<f64>(&mut rand::thread_rng()))
+    }
+}
+
+/// Drives a Thrift call with uniform resilience based on
+/// [`HasErrorHandlingStrategy::get_error_handling_strategy`], so every call
+/// site (`ChangesSinceV2`, `GetScmStatusV2`, `Unmount`, ...) gets the same
+/// treatment instead of ad-hoc retry logic: `Retry` backs off and re-issues
+/// `make_request`; `Reconnect` runs `reconnect` first, then re-issues;
+/// `Abort` returns the error immediately. Also treats
+/// `EdenFsError::ThriftConnectionTimeout`/`RequestTimeout`/`ThriftIoError` as
+/// `Reconnect`-worthy regardless of what `E` itself reports, since those are
+/// exactly the situations a reconnect should fix. Gives up once
+/// `policy.max_attempts` is reached, returning the last error.
+pub async fn retry_with_strategy<F, Fut, T, E, Reconnect, ReconnectFut>(
+    mut make_request: F,
+    mut reconnect: Reconnect,
+    policy: RetryStrategyPolicy,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = StdResult<T, E>>,
+    E: HasErrorHandlingStrategy + Into<EdenFsError>,
+    Reconnect: FnMut() -> ReconnectFut,
+    ReconnectFut: std::future::Future<Output = Result<()>>,
+{
+    let mut attempt = 0;
+    loop {
+        match make_request().await {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                let strategy = e.get_error_handling_strategy();
+                let eden_err: EdenFsError = e.into();
+                let strategy = match &eden_err {
+                    EdenFsError::ThriftConnectionTimeout(_)
+                    | EdenFsError::RequestTimeout(_)
+                    | EdenFsError::ThriftIoError(_) => ErrorHandlingStrategy::Reconnect,
+                    _ => strategy,
+                };
+
+                if strategy == ErrorHandlingStrategy::Abort || attempt + 1 >= policy.max_attempts {
+                    return Err(eden_err);
+                }
+                attempt += 1;
+
+                if strategy == ErrorHandlingStrategy::Reconnect {
+                    reconnect().await?;
+                }
+                tokio::time::sleep(policy.full_jitter_delay(attempt)).await;
+            }
+        }
+    }
+}
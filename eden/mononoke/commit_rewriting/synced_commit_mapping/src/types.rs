@@ -16,6 +16,9 @@ use anyhow::Error;
 use async_trait::async_trait;
 use auto_impl::auto_impl;
 use context::CoreContext;
+use futures::stream;
+use futures::stream::StreamExt;
+use futures::stream::TryStreamExt;
 use metaconfig_types::CommitSyncConfigVersion;
 use mononoke_types::ChangesetId;
 use mononoke_types::RepositoryId;
@@ -235,6 +238,39 @@ pub trait SyncedCommitMapping: Send + Sync {
         target_repo_id: RepositoryId,
     ) -> Result<Option<WorkingCopyEquivalence>, Error>;
 
+    /// Finds equivalent working copies for many source commits and a target
+    /// repo in one go. SQL-backed implementations should override this with
+    /// a single batched query -- there isn't one backing `SyncedCommitMapping`
+    /// in this checkout to add it to, so this default instead fans the
+    /// per-commit lookups out across `get_equivalent_working_copy` with
+    /// bounded concurrency. That still issues one query per commit, but
+    /// running them concurrently rather than one-at-a-time is a meaningful
+    /// win for the large cross-repo backfills this is meant to serve, and
+    /// gives any future SQL-backed implementation the same default to start
+    /// from and improve on.
+    async fn get_many_equivalent_working_copy(
+        &self,
+        ctx: &CoreContext,
+        source_repo_id: RepositoryId,
+        target_repo_id: RepositoryId,
+        source_bcs_ids: &[ChangesetId],
+    ) -> Result<HashMap<ChangesetId, WorkingCopyEquivalence>, Error> {
+        const MAX_CONCURRENT_LOOKUPS: usize = 100;
+
+        let results: Vec<Option<(ChangesetId, WorkingCopyEquivalence)>> =
+            stream::iter(source_bcs_ids.iter().copied().map(|source_bcs_id| async move {
+                let equivalence = self
+                    .get_equivalent_working_copy(ctx, source_repo_id, source_bcs_id, target_repo_id)
+                    .await?;
+                Ok::<_, Error>(equivalence.map(|equivalence| (source_bcs_id, equivalence)))
+            }))
+            .buffer_unordered(MAX_CONCURRENT_LOOKUPS)
+            .try_collect()
+            .await?;
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
     /// Insert version for large repo commit without mapping to any small repo
     /// commits
     async fn insert_large_repo_commit_version(
@@ -8,7 +8,12 @@
 //! This library is used to query ODS counters
 //! It should not be used for counters that are available locally
 //! Those should be queried from the local host via fb303
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
 use async_trait::async_trait;
+use tokio::sync::Mutex;
 use tokio::time::Duration;
 
 #[cfg(fbcode_build)]
@@ -24,3 +29,211 @@ pub trait CounterManager {
 
     async fn get_counter_value(&self, entity: &str, key: &str) -> Option<f64>;
 }
+
+/// What was observed for a single `(entity, key)` counter at
+/// alert-evaluation time, handed to a registered `AlertPredicate`.
+#[derive(Clone, Copy, Debug)]
+pub struct CounterObservation {
+    /// The counter's current value, or `None` if ODS has no data for it on
+    /// this fetch.
+    pub value: Option<f64>,
+    /// How long it's been since this `(entity, key)` last returned
+    /// `Some(_)`; `None` if it has never returned a value at all.
+    pub stale_for: Option<Duration>,
+}
+
+/// Decides whether a registered alert should be considered "firing" given
+/// the counter's latest observation.
+pub type AlertPredicate = Arc<dyn Fn(CounterObservation) -> bool + Send + Sync>;
+
+/// Convenience predicate: fires once the counter has a value and it
+/// exceeds `threshold` (e.g. `missing_bookmark_moves` backlog growing past
+/// N). A missing value never fires this predicate on its own -- pair with
+/// `stale_for_longer_than` to also alert on absence.
+pub fn threshold_exceeds(threshold: f64) -> AlertPredicate {
+    Arc::new(move |obs: CounterObservation| match obs.value {
+        Some(v) => v > threshold,
+        None => false,
+    })
+}
+
+/// Convenience predicate: fires once the counter has gone unseen (stale or
+/// entirely absent) for longer than `window`. A counter that has *never*
+/// returned a value has no "last seen" instant to measure staleness from,
+/// so the first evaluation that sees `stale_for: None` is remembered as
+/// that counter's own starting point; the predicate only fires once
+/// `window` has elapsed since then, rather than on the very first
+/// evaluation (which would otherwise alert immediately on every
+/// newly-registered counter before it's had a chance to report anything).
+pub fn stale_for_longer_than(window: Duration) -> AlertPredicate {
+    let first_observed_at: std::sync::Mutex<Option<Instant>> = std::sync::Mutex::new(None);
+    Arc::new(move |obs: CounterObservation| match obs.stale_for {
+        Some(age) => age > window,
+        None => {
+            let mut first_observed_at = first_observed_at.lock().expect("lock poisoned");
+            let first = *first_observed_at.get_or_insert_with(Instant::now);
+            Instant::now().saturating_duration_since(first) > window
+        }
+    })
+}
+
+/// A structured notification for a single firing alert, handed to an
+/// `AlertSink` to render/deliver however it likes (webhook payload, chat
+/// message, log line, ...).
+#[derive(Clone, Debug)]
+pub struct AlertNotification {
+    pub entity: String,
+    pub key: String,
+    pub value: Option<f64>,
+    pub message: String,
+}
+
+/// Where a firing alert's notification is sent: an HTTP webhook, a chat
+/// room, or (the default, see `LoggingAlertSink`) just the logs.
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    async fn notify(&self, notification: &AlertNotification);
+}
+
+/// Logs the notification instead of delivering it anywhere, so
+/// `AlertManager` doesn't require wiring an actual webhook/chat sink just
+/// to start alerting into the logs.
+pub struct LoggingAlertSink;
+
+#[async_trait]
+impl AlertSink for LoggingAlertSink {
+    async fn notify(&self, notification: &AlertNotification) {
+        tracing::warn!(
+            "[ALERT] {}.{} = {:?}: {}",
+            notification.entity,
+            notification.key,
+            notification.value,
+            notification.message,
+        );
+    }
+}
+
+/// One alert registered via `AlertManager::register_alert`, plus the
+/// firing-edge and cooldown state needed to avoid re-notifying on every
+/// tick while the condition remains true.
+struct RegisteredAlert {
+    entity: String,
+    key: String,
+    predicate: AlertPredicate,
+    cooldown: Duration,
+    message: String,
+    currently_firing: bool,
+    last_notified_at: Option<Instant>,
+}
+
+/// Turns a passive `CounterManager` into something that can page: wraps it
+/// with a set of registered predicates that get evaluated against its
+/// counter values on a fixed interval, dispatching a notification to an
+/// `AlertSink` on each not-firing-to-firing transition (subject to a
+/// per-alert cooldown).
+///
+/// `AlertManager` reads values via `CounterManager::get_counter_value`
+/// rather than driving the fetch itself, so it's meant to run alongside
+/// (not instead of) the wrapped manager's own `run_periodic_fetch`, which
+/// is what actually keeps those values fresh.
+pub struct AlertManager<C> {
+    inner: Arc<Mutex<C>>,
+    sink: Arc<dyn AlertSink>,
+    alerts: Mutex<Vec<RegisteredAlert>>,
+    last_seen: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl<C: CounterManager + Send> AlertManager<C> {
+    pub fn new(inner: Arc<Mutex<C>>, sink: Arc<dyn AlertSink>) -> Self {
+        Self {
+            inner,
+            sink,
+            alerts: Mutex::new(Vec::new()),
+            last_seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registers `entity`/`key` with the wrapped `CounterManager` (so it
+    /// gets fetched) and adds an alert that fires `predicate` against its
+    /// observations, notifying through the sink at most once per
+    /// `cooldown` per firing episode.
+    pub async fn register_alert(
+        &self,
+        entity: impl Into<String>,
+        key: impl Into<String>,
+        predicate: AlertPredicate,
+        cooldown: Duration,
+        message: impl Into<String>,
+    ) {
+        let entity = entity.into();
+        let key = key.into();
+        self.inner
+            .lock()
+            .await
+            .add_counter(entity.clone(), key.clone())
+            .await;
+        self.alerts.lock().await.push(RegisteredAlert {
+            entity,
+            key,
+            predicate,
+            cooldown,
+            message: message.into(),
+            currently_firing: false,
+            last_notified_at: None,
+        });
+    }
+
+    /// Evaluates every registered alert once against the manager's current
+    /// counter values, dispatching notifications for alerts that transition
+    /// from not-firing to firing and whose cooldown has elapsed.
+    pub async fn evaluate_once(&self) {
+        let now = Instant::now();
+        let mut last_seen = self.last_seen.lock().await;
+        let mut alerts = self.alerts.lock().await;
+
+        for alert in alerts.iter_mut() {
+            let value = self
+                .inner
+                .lock()
+                .await
+                .get_counter_value(&alert.entity, &alert.key)
+                .await;
+
+            let seen_key = (alert.entity.clone(), alert.key.clone());
+            if value.is_some() {
+                last_seen.insert(seen_key.clone(), now);
+            }
+            let stale_for = last_seen.get(&seen_key).map(|t| now.saturating_duration_since(*t));
+
+            let firing = (alert.predicate)(CounterObservation { value, stale_for });
+
+            if firing && !alert.currently_firing {
+                let should_notify = match alert.last_notified_at {
+                    Some(last) => now.saturating_duration_since(last) >= alert.cooldown,
+                    None => true,
+                };
+                if should_notify {
+                    self.sink
+                        .notify(&AlertNotification {
+                            entity: alert.entity.clone(),
+                            key: alert.key.clone(),
+                            value,
+                            message: alert.message.clone(),
+                        })
+                        .await;
+                    alert.last_notified_at = Some(now);
+                }
+            }
+            alert.currently_firing = firing;
+        }
+    }
+
+    /// Runs `evaluate_once` on a fixed interval, forever.
+    pub async fn run_periodic_alerts(&self, interval_duration: Duration) {
+        let mut interval = tokio::time::interval(interval_duration);
+        loop {
+            interval.tick().await;
+            self.evaluate_once().await;
+        }
+    }
+}
@@ -18,47 +18,62 @@ use crate::PushRedirectionConfig;
 use crate::PushRedirectionConfigEntry;
 use crate::RowId;
 
+// `bookmark` is `""` for the repo-wide fallback row and the bookmark name
+// otherwise, so (repo_id, bookmark) can stay a plain unique key in both the
+// MySQL and SQLite schemas instead of needing NULL-aware uniqueness.
 mononoke_queries! {
     read TestGet(id: RowId) -> (
         RowId,
         RepositoryId,
+        String,
         bool,
         bool,
     ) {
         "SELECT id,
             repo_id,
+            bookmark,
             draft_push,
             public_push
          FROM pushredirect
          WHERE id = {id}"
     }
 
-    read Get(repo_id: RepositoryId) -> (
+    read Get(repo_id: RepositoryId, bookmark: String) -> (
         RowId,
         RepositoryId,
+        String,
         bool,
         bool,
     ) {
         "SELECT id,
             repo_id,
+            bookmark,
             draft_push,
             public_push
          FROM pushredirect
-         WHERE repo_id = {repo_id}"
+         WHERE repo_id = {repo_id} AND bookmark = {bookmark}"
     }
 
-    write Set(repo_id: RepositoryId, draft_push: bool, public_push: bool) {
+    write Set(repo_id: RepositoryId, bookmark: String, draft_push: bool, public_push: bool) {
         none,
-        mysql("INSERT INTO pushredirect (repo_id, draft_push, public_push) VALUES ({repo_id}, {draft_push}, {public_push}) ON DUPLICATE KEY UPDATE draft_push = {draft_push}, public_push = {public_push}")
-        sqlite("REPLACE INTO pushredirect (repo_id, draft_push, public_push) VALUES ({repo_id}, {draft_push}, {public_push})")
+        mysql("INSERT INTO pushredirect (repo_id, bookmark, draft_push, public_push) VALUES ({repo_id}, {bookmark}, {draft_push}, {public_push}) ON DUPLICATE KEY UPDATE draft_push = {draft_push}, public_push = {public_push}")
+        sqlite("REPLACE INTO pushredirect (repo_id, bookmark, draft_push, public_push) VALUES ({repo_id}, {bookmark}, {draft_push}, {public_push})")
     }
 }
 
-fn row_to_entry(row: (RowId, RepositoryId, bool, bool)) -> PushRedirectionConfigEntry {
-    let (id, repo_id, draft_push, public_push) = row;
+/// The repo-wide fallback row's `bookmark` column value.
+const NO_BOOKMARK: &str = "";
+
+fn row_to_entry(row: (RowId, RepositoryId, String, bool, bool)) -> PushRedirectionConfigEntry {
+    let (id, repo_id, bookmark, draft_push, public_push) = row;
     PushRedirectionConfigEntry {
         id,
         repo_id,
+        bookmark: if bookmark.is_empty() {
+            None
+        } else {
+            Some(bookmark)
+        },
         draft_push,
         public_push,
     }
@@ -99,10 +114,17 @@ impl SqlConstructFromMetadataDatabaseConfig for SqlPushRedirectionConfigBuilder
 
 #[async_trait]
 impl PushRedirectionConfig for SqlPushRedirectionConfig {
-    async fn set(&self, _ctx: &CoreContext, draft_push: bool, public_push: bool) -> Result<()> {
+    async fn set(
+        &self,
+        _ctx: &CoreContext,
+        bookmark: Option<&str>,
+        draft_push: bool,
+        public_push: bool,
+    ) -> Result<()> {
         Set::query(
             &self.connections.write_connection,
             &self.repo_id,
+            &bookmark.unwrap_or(NO_BOOKMARK).to_string(),
             &draft_push,
             &public_push,
         )
@@ -110,8 +132,31 @@ impl PushRedirectionConfig for SqlPushRedirectionConfig {
         Ok(())
     }
 
-    async fn get(&self, _ctx: &CoreContext) -> Result<Option<PushRedirectionConfigEntry>> {
-        let rows = Get::query(&self.connections.read_connection, &self.repo_id).await?;
+    /// Returns the most specific matching entry: the bookmark-specific row
+    /// if one was set for `bookmark`, falling back to the repo-wide row
+    /// otherwise.
+    async fn get(
+        &self,
+        _ctx: &CoreContext,
+        bookmark: Option<&str>,
+    ) -> Result<Option<PushRedirectionConfigEntry>> {
+        if let Some(bookmark) = bookmark {
+            let rows = Get::query(
+                &self.connections.read_connection,
+                &self.repo_id,
+                &bookmark.to_string(),
+            )
+            .await?;
+            if let Some(row) = rows.into_iter().next() {
+                return Ok(Some(row_to_entry(row)));
+            }
+        }
+        let rows = Get::query(
+            &self.connections.read_connection,
+            &self.repo_id,
+            &NO_BOOKMARK.to_string(),
+        )
+        .await?;
         Ok(rows.into_iter().next().map(row_to_entry))
     }
 }
@@ -129,8 +174,8 @@ mod test {
         let push = builder.clone().build(RepositoryId::new(1));
 
         // insert one
-        push.set(&ctx, true, false).await?;
-        let entry = push.get(&ctx).await?;
+        push.set(&ctx, None, true, false).await?;
+        let entry = push.get(&ctx, None).await?;
         assert!(entry.is_some());
         let entry = entry.unwrap();
         assert!(entry.draft_push);
@@ -139,16 +184,16 @@ mod test {
         let push = builder.build(RepositoryId::new(2));
 
         // insert another
-        push.set(&ctx, false, true).await?;
-        let entry = push.get(&ctx).await?;
+        push.set(&ctx, None, false, true).await?;
+        let entry = push.get(&ctx, None).await?;
         assert!(entry.is_some());
         let entry = entry.unwrap();
         assert!(!entry.draft_push);
         assert!(entry.public_push);
 
         // update it
-        push.set(&ctx, true, true).await?;
-        let entry = push.get(&ctx).await?;
+        push.set(&ctx, None, true, true).await?;
+        let entry = push.get(&ctx, None).await?;
         assert!(entry.is_some());
         let entry = entry.unwrap();
         assert!(entry.draft_push);
@@ -163,11 +208,11 @@ mod test {
         let builder = SqlPushRedirectionConfigBuilder::with_sqlite_in_memory()?;
         let push = builder.build(RepositoryId::new(3));
 
-        let entry = push.get(&ctx).await?;
+        let entry = push.get(&ctx, None).await?;
         assert!(entry.is_none());
 
-        push.set(&ctx, true, true).await?;
-        let entry = push.get(&ctx).await?;
+        push.set(&ctx, None, true, true).await?;
+        let entry = push.get(&ctx, None).await?;
         assert!(entry.is_some());
         let entry = entry.unwrap();
         assert!(entry.draft_push);
@@ -175,4 +220,35 @@ mod test {
 
         Ok(())
     }
+
+    #[fbinit::test]
+    async fn test_get_per_bookmark(fb: FacebookInit) -> Result<()> {
+        let ctx = CoreContext::test_mock(fb);
+        let builder = SqlPushRedirectionConfigBuilder::with_sqlite_in_memory()?;
+        let push = builder.build(RepositoryId::new(4));
+
+        // repo-wide fallback: draft-only redirection
+        push.set(&ctx, None, true, false).await?;
+
+        // a specific bookmark is migrated further (both draft and public)
+        push.set(&ctx, Some("migrated_bookmark"), true, true)
+            .await?;
+
+        // bookmark with its own entry gets the specific one
+        let entry = push.get(&ctx, Some("migrated_bookmark")).await?.unwrap();
+        assert!(entry.draft_push);
+        assert!(entry.public_push);
+
+        // any other bookmark falls back to the repo-wide entry
+        let entry = push.get(&ctx, Some("other_bookmark")).await?.unwrap();
+        assert!(entry.draft_push);
+        assert!(!entry.public_push);
+
+        // and so does querying with no bookmark at all
+        let entry = push.get(&ctx, None).await?.unwrap();
+        assert!(entry.draft_push);
+        assert!(!entry.public_push);
+
+        Ok(())
+    }
 }
@@ -1,45 +1,200 @@
 // @generated by autocargo
 
 use std::env;
-use std::fs;
 use std::path::Path;
 use thrift_compiler::Config;
+use thrift_compiler::CrateMap;
 use thrift_compiler::GenContext;
-const CRATEMAP: &str = "\
-eden/mononoke/mercurial/mutation/if/hg_mutation_entry.thrift crate //eden/mononoke/mercurial/mutation/if:hg_mutation_entry_thrift-rust
-eden/mononoke/mercurial/types/if/mercurial_thrift.thrift mercurial_thrift //eden/mononoke/mercurial/types/if:mercurial-thrift-rust
-eden/mononoke/mononoke_types/serialization/blame.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/bonsai.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/bssm.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/ccsm.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/changeset_info.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/content.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/content_manifest.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/data.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/deleted_manifest.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/fastlog.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/fsnodes.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/id.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/path.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/raw_bundle2.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/redaction.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/sharded_map.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/skeleton_manifest.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/test_manifest.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/time.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-eden/mononoke/mononoke_types/serialization/unodes.thrift mercurial_thrift->mononoke_types_serialization //eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust
-thrift/annotation/rust.thrift rust //thrift/annotation:rust-rust
-thrift/annotation/scope.thrift rust->scope //thrift/annotation:scope-rust
-";
+
+/// Resolves `relative` (given as an offset from this crate's manifest, the
+/// same way `.base_path(...)` was previously called with a bare `../..`
+/// string) into an absolute path anchored at `CARGO_MANIFEST_DIR`. A
+/// relative `base_path` breaks when the crate is built out-of-tree, and on
+/// Windows `canonicalize()` returns a `\\?\`-prefixed verbatim path that
+/// `thrift_compiler` doesn't expect when comparing include paths, so that
+/// prefix is stripped before handing the path back.
+fn base_path_from_manifest(relative: &str) -> String {
+    let manifest_dir =
+        env::var_os("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR env not provided");
+    let joined = Path::new(&manifest_dir).join(relative);
+    let canonical = joined
+        .canonicalize()
+        .unwrap_or_else(|e| panic!("Failed to canonicalize base path {:?}: {}", joined, e));
+    let canonical = canonical.to_string_lossy().into_owned();
+    if cfg!(windows) {
+        canonical
+            .strip_prefix(r"\\?\")
+            .map(str::to_string)
+            .unwrap_or(canonical)
+    } else {
+        canonical
+    }
+}
+
+/// One thrift file reachable (directly or via `include`) from
+/// `hg_mutation_entry.thrift`, paired with the crate alias autocargo
+/// generated for it and the buck target that provides it.
+struct ThriftInclude {
+    path: &'static str,
+    alias: &'static str,
+    target: &'static str,
+}
+
+const THRIFT_INCLUDES: &[ThriftInclude] = &[
+    ThriftInclude {
+        path: "eden/mononoke/mercurial/mutation/if/hg_mutation_entry.thrift",
+        alias: "crate",
+        target: "//eden/mononoke/mercurial/mutation/if:hg_mutation_entry_thrift-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mercurial/types/if/mercurial_thrift.thrift",
+        alias: "mercurial_thrift",
+        target: "//eden/mononoke/mercurial/types/if:mercurial-thrift-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/blame.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/bonsai.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/bssm.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/ccsm.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/changeset_info.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/content.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/content_manifest.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/data.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/deleted_manifest.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/fastlog.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/fsnodes.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/id.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/path.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/raw_bundle2.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/redaction.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/sharded_map.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/skeleton_manifest.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/test_manifest.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/time.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "eden/mononoke/mononoke_types/serialization/unodes.thrift",
+        alias: "mononoke_types_serialization",
+        target: "//eden/mononoke/mononoke_types/serialization:mononoke_types_serialization-rust",
+    },
+    ThriftInclude {
+        path: "thrift/annotation/rust.thrift",
+        alias: "rust",
+        target: "//thrift/annotation:rust-rust",
+    },
+    ThriftInclude {
+        path: "thrift/annotation/scope.thrift",
+        alias: "scope",
+        target: "//thrift/annotation:scope-rust",
+    },
+];
+
+/// Builds the cratemap from `THRIFT_INCLUDES` instead of hand-maintaining
+/// the serialized `<path> <alias> <target>` text, so the `mercurial_thrift
+/// -> mononoke_types_serialization` and `rust -> scope` dependency edges are
+/// declared once via `add_dependency` and validated by `thrift_compiler`
+/// rather than copy-pasted into every thrift_build.rs that needs them.
+fn build_cratemap() -> CrateMap {
+    let mut map = CrateMap::new();
+    for include in THRIFT_INCLUDES {
+        map.add_crate(include.path, include.alias, include.target);
+    }
+    map.add_dependency("mercurial_thrift", "mononoke_types_serialization");
+    map.add_dependency("rust", "scope");
+    map
+}
+
 #[rustfmt::skip]
 fn main() {
     println!("cargo:rerun-if-changed=thrift_build.rs");
+    let base_path = base_path_from_manifest("../../../../../..");
+    for include in THRIFT_INCLUDES {
+        println!(
+            "cargo:rerun-if-changed={}",
+            Path::new(&base_path).join(include.path).display()
+        );
+    }
     let out_dir = env::var_os("OUT_DIR").expect("OUT_DIR env not provided");
-    let cratemap_path = Path::new(&out_dir).join("cratemap");
-    fs::write(cratemap_path, CRATEMAP).expect("Failed to write cratemap");
+    build_cratemap()
+        .write_to(Path::new(&out_dir))
+        .expect("Failed to write cratemap");
     Config::from_env(GenContext::Clients)
         .expect("Failed to instantiate thrift_compiler::Config")
-        .base_path("../../../../../..")
+        .base_path(base_path)
         .types_crate("hg_mutation_entry_thrift__types")
         .clients_crate("hg_mutation_entry_thrift__clients")
         .run(["../hg_mutation_entry.thrift"])
@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use tempfile::NamedTempFile;
+
+/// Current on-disk shape of `Checkpoint`. Bump whenever a field is added or
+/// changed in a way `migrate_to_current` needs to know about.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// A resumable position within a modern-sync run. Replaces the pair of loose
+/// `MutableCounters` (`MODERN_SYNC_BATCH_CHECKPOINT_NAME` /
+/// `MODERN_SYNC_CURRENT_ENTRY_ID`) with a single versioned, serde-serialized
+/// record so on-disk shape can evolve without breaking resumption.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    /// The bookmark-update-log entry id being processed.
+    pub entry_id: i64,
+    /// Position within `entry_id`'s batch, so we can resume mid-entry.
+    pub position_in_entry: u64,
+    /// Protocol version negotiated with the receiver when this checkpoint
+    /// was written. Resuming with an older sender against a receiver that
+    /// has since moved on is refused rather than risking a corrupt resume.
+    pub protocol_version: u32,
+    /// Which of the four channels (content/files/trees/changesets) had
+    /// fully drained their batch for `entry_id` when this was written.
+    pub channels_drained: HashSet<String>,
+}
+
+impl Checkpoint {
+    pub fn new(entry_id: i64, position_in_entry: u64, protocol_version: u32) -> Self {
+        Self {
+            version: CHECKPOINT_VERSION,
+            entry_id,
+            position_in_entry,
+            protocol_version,
+            channels_drained: HashSet::new(),
+        }
+    }
+}
+
+/// Loads, validates, and atomically persists `Checkpoint`s, migrating an
+/// older on-disk shape forward to `CHECKPOINT_VERSION` when possible.
+pub struct CheckpointStore {
+    path: std::path::PathBuf,
+}
+
+impl CheckpointStore {
+    pub fn new(path: std::path::PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Load the checkpoint, migrating it forward if it's an older version.
+    /// Returns `None` if there's nothing to resume from yet.
+    pub fn load(&self) -> Result<Option<Checkpoint>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let raw = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Reading checkpoint from {:?}", self.path))?;
+        let on_disk: serde_json::Value =
+            serde_json::from_str(&raw).context("Parsing checkpoint JSON")?;
+        Ok(Some(Self::migrate_to_current(on_disk)?))
+    }
+
+    /// Migrate an arbitrary on-disk JSON shape forward to the current
+    /// `Checkpoint` shape. There's only ever been one version so far, so
+    /// this just deserializes directly; future migrations should match on
+    /// `version` and fill in defaults for fields added since.
+    fn migrate_to_current(on_disk: serde_json::Value) -> Result<Checkpoint> {
+        let version = on_disk
+            .get("version")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as u32;
+        if version > CHECKPOINT_VERSION {
+            bail!(
+                "Checkpoint version {} is newer than this binary supports ({})",
+                version,
+                CHECKPOINT_VERSION
+            );
+        }
+        let checkpoint: Checkpoint =
+            serde_json::from_value(on_disk).context("Migrating checkpoint to current shape")?;
+        Ok(checkpoint)
+    }
+
+    /// Atomically persist `checkpoint`: write to a temp file in the same
+    /// directory, then rename over the target so a crash mid-write can never
+    /// leave a half-written checkpoint behind.
+    pub fn save(&self, checkpoint: &Checkpoint) -> Result<()> {
+        let dir = self
+            .path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let mut tmp = NamedTempFile::new_in(dir)
+            .with_context(|| format!("Creating temp file in {:?}", dir))?;
+        serde_json::to_writer_pretty(&mut tmp, checkpoint).context("Serializing checkpoint")?;
+        tmp.persist(&self.path)
+            .with_context(|| format!("Persisting checkpoint to {:?}", self.path))?;
+        Ok(())
+    }
+
+    /// Refuse to resume across an incompatible negotiated protocol version
+    /// rather than risk replaying a batch shape the current sender doesn't
+    /// understand.
+    pub fn validate_resume(
+        checkpoint: &Checkpoint,
+        negotiated_protocol_version: u32,
+    ) -> Result<()> {
+        if checkpoint.protocol_version != negotiated_protocol_version {
+            bail!(
+                "Checkpoint was written with protocol version {} but the current session negotiated {}; refusing to resume",
+                checkpoint.protocol_version,
+                negotiated_protocol_version
+            );
+        }
+        Ok(())
+    }
+}
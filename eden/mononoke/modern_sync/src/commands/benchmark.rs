@@ -5,13 +5,16 @@
  * GNU General Public License version 2.
  */
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-#[cfg(fbcode_build)]
 use std::time::Duration;
+use std::time::Instant;
 
+use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
 use clap::Parser;
@@ -25,6 +28,7 @@ use metadata::Metadata;
 use mononoke_app::args::MonitoringArgs;
 use mononoke_app::MononokeApp;
 use mutable_counters::MutableCounters;
+use serde::Serialize;
 
 #[cfg(fbcode_build)]
 mod stats;
@@ -38,18 +42,37 @@ use crate::sync::get_unsharded_repo_args;
 use crate::sync::ExecutionType;
 use crate::ModernSyncArgs;
 
-#[derive(ValueEnum, Default, Clone)]
-enum BenchmarkMode {
-    #[default]
-    Noop,
-    UploadContents,
+/// Parses an `--enable`/`--disable` value into the `MethodFilter` variant it
+/// names, so the benchmark can toggle any individual edenapi upload path
+/// without `BenchmarkMode` having to enumerate a fixed set of presets.
+fn parse_method_filter(s: &str) -> std::result::Result<MethodFilter, String> {
+    match s {
+        "upload-contents" => Ok(MethodFilter::UploadContents),
+        "upload-trees" => Ok(MethodFilter::UploadTrees),
+        "upload-changesets" => Ok(MethodFilter::UploadChangesets),
+        other => Err(format!(
+            "unknown method '{}': expected one of upload-contents, upload-trees, upload-changesets",
+            other
+        )),
+    }
 }
 
 /// Replays bookmark's moves
 #[derive(Parser)]
 pub struct CommandArgs {
-    #[clap(long, default_value_t, value_enum)]
-    mode: BenchmarkMode,
+    #[clap(
+        long = "enable",
+        value_parser = parse_method_filter,
+        help = "Edenapi upload method to enable for this benchmark run (repeatable), e.g. `--enable upload-contents --enable upload-trees`. With none given, the benchmark runs in no-op mode."
+    )]
+    enable: Vec<MethodFilter>,
+
+    #[clap(
+        long = "disable",
+        value_parser = parse_method_filter,
+        help = "Edenapi upload method to explicitly disable, overriding a matching --enable; mainly useful to carve one method out of an otherwise broad set."
+    )]
+    disable: Vec<MethodFilter>,
 
     #[clap(long, help = "Chunk size for the sync [default: 1000]")]
     chunk_size: Option<u64>,
@@ -60,6 +83,120 @@ pub struct CommandArgs {
         help = "How often to report stats, in seconds"
     )]
     stat_interval: u64,
+
+    #[clap(
+        long,
+        default_value = "1",
+        help = "Number of timed sync iterations to run"
+    )]
+    iterations: u32,
+
+    #[clap(
+        long,
+        default_value = "0",
+        help = "Number of untimed iterations to run and discard before the timed ones, to let caches/connections warm up"
+    )]
+    warmup: u32,
+
+    #[clap(
+        long,
+        help = "Write the timing summary and final counter dump to this path as structured data (see --format), so results can be diffed across commits or fed into regression tooling"
+    )]
+    output: Option<PathBuf>,
+
+    #[clap(
+        long,
+        default_value_t,
+        value_enum,
+        help = "Format for --output"
+    )]
+    format: OutputFormat,
+}
+
+#[derive(ValueEnum, Default, Clone, Copy)]
+enum OutputFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// Machine-readable benchmark result written out via `--output`: the timing
+/// summary plus the final iteration's counter dump, so results can be
+/// compared across commits or runs without scraping log lines.
+#[derive(Serialize)]
+struct BenchmarkResult {
+    source_repo: String,
+    dest_repo: String,
+    iterations: usize,
+    min_ms: u128,
+    p50_ms: u128,
+    p90_ms: u128,
+    p99_ms: u128,
+    max_ms: u128,
+    mean_ms: u128,
+    counters: BTreeMap<String, i64>,
+}
+
+fn write_benchmark_result(path: &Path, format: OutputFormat, result: &BenchmarkResult) -> Result<()> {
+    let contents = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(result)?,
+        OutputFormat::Csv => to_csv(result),
+    };
+    std::fs::write(path, contents)
+        .with_context(|| format!("Failed to write benchmark output to {:?}", path))
+}
+
+fn to_csv(result: &BenchmarkResult) -> String {
+    let mut out = String::from("metric,value\n");
+    out.push_str(&format!("source_repo,{}\n", result.source_repo));
+    out.push_str(&format!("dest_repo,{}\n", result.dest_repo));
+    out.push_str(&format!("iterations,{}\n", result.iterations));
+    out.push_str(&format!("min_ms,{}\n", result.min_ms));
+    out.push_str(&format!("p50_ms,{}\n", result.p50_ms));
+    out.push_str(&format!("p90_ms,{}\n", result.p90_ms));
+    out.push_str(&format!("p99_ms,{}\n", result.p99_ms));
+    out.push_str(&format!("max_ms,{}\n", result.max_ms));
+    out.push_str(&format!("mean_ms,{}\n", result.mean_ms));
+    for (k, v) in &result.counters {
+        out.push_str(&format!("counter.{},{}\n", k, v));
+    }
+    out
+}
+
+/// Aggregate timing over a set of benchmark iterations, reported instead of
+/// a single elapsed figure so a noisy one-off sample doesn't get mistaken
+/// for a stable measurement.
+struct LatencyStats {
+    min: Duration,
+    max: Duration,
+    mean: Duration,
+    p50: Duration,
+    p90: Duration,
+    p99: Duration,
+}
+
+impl LatencyStats {
+    /// Panics if `samples` is empty; callers always run at least one timed
+    /// iteration.
+    fn from_samples(samples: &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+
+        let percentile = |p: f64| -> Duration {
+            let idx = ((sorted.len() - 1) as f64 * p).round() as usize;
+            sorted[idx]
+        };
+        let total: Duration = sorted.iter().sum();
+
+        Self {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: total / sorted.len() as u32,
+            p50: percentile(0.50),
+            p90: percentile(0.90),
+            p99: percentile(0.99),
+        }
+    }
 }
 
 #[derive(Clone, Default)]
@@ -117,8 +254,13 @@ pub async fn run(app: MononokeApp, args: CommandArgs) -> Result<()> {
         get_unsharded_repo_args(app.clone(), app_args).await?;
     let ctx = new_context(&app);
 
-    let benchmark_mode = args.mode;
-    let mc = MemoryMutableCounters::new();
+    let mut allowed: HashMap<MethodFilter, bool> = HashMap::new();
+    for method in &args.enable {
+        allowed.insert(method.clone(), true);
+    }
+    for method in &args.disable {
+        allowed.insert(method.clone(), false);
+    }
 
     #[cfg(fbcode_build)]
     let stats = {
@@ -138,48 +280,97 @@ pub async fn run(app: MononokeApp, args: CommandArgs) -> Result<()> {
         stats
     };
 
-    let now = std::time::Instant::now();
-    let cancellation_requested = Arc::new(AtomicBool::new(false));
-    crate::sync::sync(
-        app,
-        Some(0),
-        source_repo_args,
-        dest_repo_name.clone(),
-        ExecutionType::SyncOnce,
-        false,
-        args.chunk_size.clone().unwrap_or(CHUNK_SIZE_DEFAULT),
-        PathBuf::from(""),
-        Some(Box::new(move |sender| {
-            let sender: Arc<dyn EdenapiSender + Sync + Send> = match benchmark_mode {
-                BenchmarkMode::Noop => Arc::new(NoopEdenapiSender::default()),
-                BenchmarkMode::UploadContents => {
-                    let allowed = HashMap::from([(MethodFilter::UploadContents, true)]);
-                    Arc::new(FilterEdenapiSender::new(sender, allowed))
-                }
-            };
-            sender
-        })),
-        Some(Arc::new(mc.clone())),
-        cancellation_requested,
-    )
-    .await?;
-    let elapsed = now.elapsed();
+    let total_runs = args.warmup.saturating_add(args.iterations.max(1));
+    let mut samples = Vec::with_capacity(args.iterations.max(1) as usize);
+    let mut final_counters = BTreeMap::new();
+
+    for i in 0..total_runs {
+        let mc = MemoryMutableCounters::new();
+        let cancellation_requested = Arc::new(AtomicBool::new(false));
+        let allowed = allowed.clone();
+
+        let now = Instant::now();
+        crate::sync::sync(
+            app.clone(),
+            Some(0),
+            source_repo_args.clone(),
+            dest_repo_name.clone(),
+            ExecutionType::SyncOnce,
+            false,
+            args.chunk_size.clone().unwrap_or(CHUNK_SIZE_DEFAULT),
+            PathBuf::from(""),
+            Some(Box::new(move |sender| {
+                let sender: Arc<dyn EdenapiSender + Sync + Send> =
+                    if allowed.values().any(|enabled| *enabled) {
+                        Arc::new(FilterEdenapiSender::new(sender, allowed))
+                    } else {
+                        Arc::new(NoopEdenapiSender::default())
+                    };
+                sender
+            })),
+            Some(Arc::new(mc.clone())),
+            cancellation_requested,
+        )
+        .await?;
+        let elapsed = now.elapsed();
+
+        if i < args.warmup {
+            tracing::info!("Benchmark: warmup iteration {} took {}ms", i, elapsed.as_millis());
+            continue;
+        }
+
+        samples.push(elapsed);
+        tracing::info!(
+            "Benchmark: iteration {} took {}ms",
+            i - args.warmup,
+            elapsed.as_millis()
+        );
+
+        // Only the last (timed) iteration's counters are worth dumping --
+        // each iteration starts from a fresh `MemoryMutableCounters`, so
+        // earlier ones are no longer relevant once we've moved on.
+        if i == total_runs - 1 {
+            tracing::info!("Counters:");
+            let mut counters = mc.get_all_counters(&ctx).await?;
+            counters.sort_by(|a, b| a.0.cmp(&b.0));
+            for (k, v) in counters {
+                tracing::info!("{}={}", k, v);
+                final_counters.insert(k, v);
+            }
+        }
+    }
 
     #[cfg(fbcode_build)]
     stats.finish().await;
 
+    let stats = LatencyStats::from_samples(&samples);
     tracing::info!(
-        "Benchmark: Sync {} to {:?} took {}ms",
-        elapsed.as_millis(),
+        "Benchmark: Sync {} to {:?} over {} iteration(s): min={}ms p50={}ms p90={}ms p99={}ms max={}ms mean={}ms",
         &source_repo_name,
         dest_repo_name,
+        samples.len(),
+        stats.min.as_millis(),
+        stats.p50.as_millis(),
+        stats.p90.as_millis(),
+        stats.p99.as_millis(),
+        stats.max.as_millis(),
+        stats.mean.as_millis(),
     );
 
-    tracing::info!("Counters:");
-    let mut counters = mc.get_all_counters(&ctx).await?;
-    counters.sort_by(|a, b| a.0.cmp(&b.0));
-    for (k, v) in counters {
-        tracing::info!("{}={}", k, v);
+    if let Some(output_path) = &args.output {
+        let result = BenchmarkResult {
+            source_repo: source_repo_name,
+            dest_repo: dest_repo_name,
+            iterations: samples.len(),
+            min_ms: stats.min.as_millis(),
+            p50_ms: stats.p50.as_millis(),
+            p90_ms: stats.p90.as_millis(),
+            p99_ms: stats.p99.as_millis(),
+            max_ms: stats.max.as_millis(),
+            mean_ms: stats.mean.as_millis(),
+            counters: final_counters,
+        };
+        write_benchmark_result(output_path, args.format, &result)?;
     }
 
     Ok(())
@@ -5,10 +5,13 @@
  * GNU General Public License version 2.
  */
 
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
 use anyhow::format_err;
+use anyhow::Context;
 use anyhow::Result;
 use clap::Parser;
 use clientinfo::ClientEntryPoint;
@@ -21,6 +24,7 @@ use mononoke_types::ChangesetId;
 use mutable_counters::MutableCountersArc;
 use repo_blobstore::RepoBlobstoreRef;
 use repo_identity::RepoIdentityRef;
+use tracing_subscriber::prelude::*;
 use url::Url;
 
 use crate::sender::edenapi::DefaultEdenapiSender;
@@ -35,9 +39,35 @@ use crate::Repo;
 pub struct CommandArgs {
     #[clap(long, help = "Changeset to sync")]
     cs_id: ChangesetId,
+
+    #[clap(
+        long,
+        help = "Write a folded-stack flamegraph of this changeset's sync path (blobstore reads, edenapi sends, SendManager queueing) to the given file. Opt-in: adds span-collection overhead only when set."
+    )]
+    profile_output: Option<PathBuf>,
+}
+
+/// Installs a process-global `tracing-flame` layer writing folded-stack
+/// samples to `path`, returning a guard that flushes and closes the file
+/// when dropped. Only called when `--profile-output` is passed, so the
+/// default (non-profiling) path never pays for span collection.
+fn install_flame_profiling(path: &Path) -> Result<impl Drop> {
+    let (flame_layer, guard) = tracing_flame::FlameLayer::with_file(path)
+        .with_context(|| format!("Failed to open flamegraph output file {:?}", path))?;
+    tracing_subscriber::registry()
+        .with(flame_layer)
+        .try_init()
+        .map_err(|e| format_err!("Failed to install flamegraph tracing layer: {}", e))?;
+    Ok(guard)
 }
 
 pub async fn run(app: MononokeApp, args: CommandArgs) -> Result<()> {
+    let _flame_guard = args
+        .profile_output
+        .as_ref()
+        .map(|path| install_flame_profiling(path))
+        .transpose()?;
+
     let app_args = &app.args::<ModernSyncArgs>()?;
     let repo: Repo = app.open_repo(&app_args.repo).await?;
     let _repo_id = repo.repo_identity().id();
@@ -98,23 +128,52 @@ pub async fn run(app: MononokeApp, args: CommandArgs) -> Result<()> {
         )
     };
 
+    let negotiated = sender.negotiated().clone();
+    // Select zstd only if the receiver actually advertised `zstd-content`,
+    // and `Compression::None` otherwise. `ContentManager` carries the
+    // selected codec but uploads contents uncompressed either way.
+    let compression = if negotiated.supports("zstd-content") {
+        crate::sender::manager::Compression::default()
+    } else {
+        crate::sender::manager::Compression::None
+    };
+
+    // A one-off debug run has nothing to resume across invocations, but
+    // still needs a real (non-empty) path so `CheckpointStore::load` can
+    // tell "nothing written yet" apart from "never going to look".
+    let checkpoint_path = std::env::temp_dir().join(format!("modern_sync_checkpoint_{}.json", repo_name));
+
     let mut send_manager = SendManager::new(
         ctx.clone(),
+        repo.repo_blobstore().clone(),
         sender.clone(),
-        logger.clone(),
         repo_name.clone(),
         PathBuf::from(""),
         repo.mutable_counters_arc(),
+        Arc::new(AtomicBool::new(false)),
+        compression,
+        None,
+        crate::sender::manager::SendManagerConfig::default(),
+        checkpoint_path,
+        negotiated.protocol_version,
     );
 
-    let messages =
-        crate::sync::process_one_changeset(&args.cs_id, &ctx, repo, logger, false, "").await;
-    crate::sync::send_messages_in_order(messages, &mut send_manager).await?;
+    let messages = {
+        let _span = tracing::info_span!("process_one_changeset").entered();
+        crate::sync::process_one_changeset(&args.cs_id, &ctx, repo, logger, false, "").await
+    };
+    {
+        let _span = tracing::info_span!("send_messages_in_order").entered();
+        crate::sync::send_messages_in_order(messages, &mut send_manager).await?;
+    }
     let (finish_tx, finish_rx) = oneshot::channel();
     send_manager
         .send_changeset(ChangesetMessage::NotifyCompletion(finish_tx))
         .await?;
-    finish_rx.await??;
+    {
+        let _span = tracing::info_span!("wait_for_completion").entered();
+        finish_rx.await??;
+    }
 
     Ok(())
 }
@@ -0,0 +1,108 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use mononoke_macros::mononoke;
+use slog::debug;
+use slog::error;
+use slog::Logger;
+use stats::define_stats;
+use stats::prelude::*;
+
+define_stats! {
+    prefix = "mononoke.modern_sync.maintenance";
+
+    job_runs: dynamic_timeseries("{}.{}.runs", (repo: String, job: String); Sum),
+    job_failures: dynamic_timeseries("{}.{}.failures", (repo: String, job: String); Sum),
+    job_duration_ms: dynamic_timeseries("{}.{}.duration_ms", (repo: String, job: String); Average),
+}
+
+/// A named recurring chore that runs on its own timer, independent of the
+/// inbound per-channel `mpsc` traffic: verifying uploaded trees/contents
+/// against source counts, re-flushing stale partial batches, pruning
+/// orphaned `batch_done_senders`, emitting queue-health summaries, and the
+/// like. Generalizes the "do something every N seconds" logic already
+/// embedded in `TreeManager`'s flush timer into a reusable abstraction so
+/// operators have one place to hang consistency checks and self-healing
+/// tasks.
+pub struct ScheduledJob {
+    pub name: &'static str,
+    pub interval: Duration,
+    pub run: Box<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>,
+}
+
+/// Drives a registry of `ScheduledJob`s, each on its own tokio interval, until
+/// `cancellation_requested` is set. Every tick is logged on start/finish and
+/// recorded into the `job_runs`/`job_failures`/`job_duration_ms` timeseries
+/// keyed by `(repo, job)`, so a stuck or failing job shows up the same way a
+/// stuck channel worker does.
+pub struct MaintenanceScheduler {
+    reponame: String,
+    logger: Logger,
+    jobs: Vec<ScheduledJob>,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(reponame: String, logger: Logger, jobs: Vec<ScheduledJob>) -> Self {
+        Self {
+            reponame,
+            logger,
+            jobs,
+        }
+    }
+
+    pub fn start(self, cancellation_requested: Arc<AtomicBool>) {
+        for job in self.jobs {
+            let reponame = self.reponame.clone();
+            let logger = self.logger.clone();
+            let cancellation_requested = cancellation_requested.clone();
+            mononoke::spawn_task(async move {
+                let mut timer = tokio::time::interval(job.interval);
+                while !cancellation_requested.load(Ordering::Relaxed) {
+                    timer.tick().await;
+                    debug!(logger, "Maintenance job '{}' starting", job.name);
+                    let start = Instant::now();
+                    let result = (job.run)().await;
+                    let elapsed = start.elapsed();
+
+                    STATS::job_runs.add_value(1, (reponame.clone(), job.name.to_string()));
+                    STATS::job_duration_ms.add_value(
+                        elapsed.as_millis() as i64,
+                        (reponame.clone(), job.name.to_string()),
+                    );
+                    match result {
+                        Ok(()) => {
+                            debug!(
+                                logger,
+                                "Maintenance job '{}' finished in {}ms",
+                                job.name,
+                                elapsed.as_millis()
+                            );
+                        }
+                        Err(e) => {
+                            STATS::job_failures.add_value(1, (reponame.clone(), job.name.to_string()));
+                            error!(
+                                logger,
+                                "Maintenance job '{}' failed after {}ms: {:?}",
+                                job.name,
+                                elapsed.as_millis(),
+                                e
+                            );
+                        }
+                    }
+                }
+            });
+        }
+    }
+}
@@ -7,6 +7,10 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -26,10 +30,15 @@ use mercurial_types::HgChangesetId;
 use mercurial_types::HgFileNodeId;
 use mercurial_types::HgManifestId;
 use mononoke_app::args::TLSArgs;
+use mononoke_macros::mononoke;
 use mononoke_types::FileContents;
 use repo_blobstore::RepoBlobstore;
 use slog::info;
 use slog::Logger;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::Semaphore;
+use tokio::time::interval;
 use url::Url;
 
 mod util;
@@ -37,12 +46,347 @@ mod util;
 use crate::sender::Entry;
 use crate::sender::ModernSyncSender;
 
+/// Current protocol version spoken by this sender. Bumped whenever the frame
+/// format or batch shapes change in a way an older receiver can't parse.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Lowest receiver protocol version this sender is willing to talk to. Below
+/// this, we fail the handshake up front instead of failing mid-stream.
+pub const MIN_SUPPORTED_PROTOCOL_VERSION: u32 = 1;
+
+/// Named, independently-evolvable features a receiver may or may not
+/// understand yet. The sender downgrades its behavior for any capability not
+/// present in the negotiated intersection.
+pub const KNOWN_CAPABILITIES: &[&str] = &["zstd-content", "batched-filenodes", "checkpoint-in-entry"];
+
+/// Outcome of the version/capability handshake: the receiver's protocol
+/// version and the intersection of capabilities both sides understand.
+#[derive(Clone, Debug, Default)]
+pub struct NegotiatedCapabilities {
+    pub protocol_version: u32,
+    pub capabilities: HashSet<String>,
+}
+
+impl NegotiatedCapabilities {
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.contains(capability)
+    }
+}
+
+/// Tunable knobs for the background pipeline `enqueue_entry` feeds: how many
+/// `Entry`s may queue before producers block (backpressure), the per-category
+/// batch size / flush interval (whichever comes first), and how many batches
+/// may be in flight to the server concurrently (our stand-in for a
+/// connection pool, since each batch upload is its own HTTP round-trip).
+#[derive(Clone, Copy, Debug)]
+pub struct EntryPipelineConfig {
+    pub channel_capacity: usize,
+    pub max_batch_size: usize,
+    pub flush_interval: Duration,
+    pub max_concurrent_batches: usize,
+}
+
+impl Default for EntryPipelineConfig {
+    fn default() -> Self {
+        Self {
+            channel_capacity: 1_000,
+            max_batch_size: 200,
+            flush_interval: Duration::from_secs(1),
+            max_concurrent_batches: 4,
+        }
+    }
+}
+
+/// Per-category counts of `Entry` values the pipeline has handed off to the
+/// server, for progress reporting.
+#[derive(Default)]
+struct EntryUploadCounts {
+    contents: AtomicU64,
+    trees: AtomicU64,
+    filenodes: AtomicU64,
+    changesets: AtomicU64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EntryUploadCountsSnapshot {
+    pub contents: u64,
+    pub trees: u64,
+    pub filenodes: u64,
+    pub changesets: u64,
+}
+
+impl EntryUploadCounts {
+    fn snapshot(&self) -> EntryUploadCountsSnapshot {
+        EntryUploadCountsSnapshot {
+            contents: self.contents.load(Ordering::Relaxed),
+            trees: self.trees.load(Ordering::Relaxed),
+            filenodes: self.filenodes.load(Ordering::Relaxed),
+            changesets: self.changesets.load(Ordering::Relaxed),
+        }
+    }
+}
+
+enum PipelineMessage {
+    Entry(Entry),
+    /// Force a flush of every partially-filled batch and wait for all
+    /// in-flight uploads to complete before acking.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Handle to the background task that drains `enqueue_entry`'s channel.
+/// Lets callers force a flush of any partially-filled batches and observe
+/// per-category upload counts for progress reporting.
+#[derive(Clone)]
+struct EntryPipelineHandle {
+    entry_tx: mpsc::Sender<PipelineMessage>,
+    counts: Arc<EntryUploadCounts>,
+}
+
+impl EntryPipelineHandle {
+    /// Awaits on the bounded channel, so a producer blocks (rather than
+    /// erroring or dropping work) once the pipeline falls behind.
+    async fn send(&self, entry: Entry) -> Result<()> {
+        self.entry_tx
+            .send(PipelineMessage::Entry(entry))
+            .await
+            .map_err(|_| anyhow::anyhow!("enqueue_entry pipeline has shut down"))
+    }
+
+    /// Waits until every `Entry` enqueued so far -- including whatever is
+    /// sitting in a partially-filled batch -- has gone through an upload
+    /// attempt. A batch whose upload fails is logged and dropped; the
+    /// failure is not reported here.
+    async fn flush(&self) -> Result<()> {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.entry_tx
+            .send(PipelineMessage::Flush(ack_tx))
+            .await
+            .map_err(|_| anyhow::anyhow!("enqueue_entry pipeline has shut down"))?;
+        ack_rx
+            .await
+            .map_err(|_| anyhow::anyhow!("enqueue_entry pipeline dropped before flushing"))
+    }
+
+    fn counts(&self) -> EntryUploadCountsSnapshot {
+        self.counts.snapshot()
+    }
+}
+
+/// Spawns the background task backing `enqueue_entry`: it drains `entry_rx`,
+/// coalesces entries into per-category batches, and flushes each one (on a
+/// size or time threshold, whichever comes first) through the same
+/// `do_upload_*` helpers the `ModernSyncSender` trait methods use. Flushes
+/// run as their own tasks, bounded by `semaphore`, so up to
+/// `max_concurrent_batches` batches can be in flight to the server at once
+/// without the producer side stalling on a single slow round-trip.
+fn spawn_entry_pipeline(
+    client: Client,
+    logger: Logger,
+    ctx: CoreContext,
+    repo_blobstore: RepoBlobstore,
+    config: EntryPipelineConfig,
+) -> EntryPipelineHandle {
+    let (entry_tx, mut entry_rx) = mpsc::channel(config.channel_capacity);
+    let counts = Arc::new(EntryUploadCounts::default());
+    let semaphore = Arc::new(Semaphore::new(config.max_concurrent_batches.max(1)));
+
+    let handle = EntryPipelineHandle {
+        entry_tx,
+        counts: counts.clone(),
+    };
+
+    mononoke::spawn_task(async move {
+        let mut contents: Vec<(AnyFileContentId, FileContents)> = Vec::new();
+        let mut trees: Vec<HgManifestId> = Vec::new();
+        let mut filenodes: Vec<HgFileNodeId> = Vec::new();
+        let mut changesets: Vec<HgBlobChangeset> = Vec::new();
+        let mut in_flight = Vec::new();
+        let mut timer = interval(config.flush_interval);
+
+        loop {
+            tokio::select! {
+                msg = entry_rx.recv() => {
+                    match msg {
+                        Some(PipelineMessage::Entry(entry)) => {
+                            match entry {
+                                Entry::Content(id, data) => contents.push((id, data)),
+                                Entry::Tree(mf_id) => trees.push(mf_id),
+                                Entry::FileNode(fn_id) => filenodes.push(fn_id),
+                                Entry::Changeset(cs) => changesets.push(cs),
+                            }
+                            if contents.len() >= config.max_batch_size {
+                                spawn_flush_contents(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut contents, &mut in_flight);
+                            }
+                            if trees.len() >= config.max_batch_size {
+                                spawn_flush_trees(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut trees, &mut in_flight);
+                            }
+                            if filenodes.len() >= config.max_batch_size {
+                                spawn_flush_filenodes(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut filenodes, &mut in_flight);
+                            }
+                            if changesets.len() >= config.max_batch_size {
+                                spawn_flush_changesets(&client, &logger, &counts, &semaphore, &mut changesets, &mut in_flight);
+                            }
+                        }
+                        Some(PipelineMessage::Flush(ack)) => {
+                            spawn_flush_contents(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut contents, &mut in_flight);
+                            spawn_flush_trees(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut trees, &mut in_flight);
+                            spawn_flush_filenodes(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut filenodes, &mut in_flight);
+                            spawn_flush_changesets(&client, &logger, &counts, &semaphore, &mut changesets, &mut in_flight);
+                            for handle in in_flight.drain(..) {
+                                let _ = handle.await;
+                            }
+                            let _ = ack.send(());
+                        }
+                        None => break,
+                    }
+                }
+                _ = timer.tick() => {
+                    spawn_flush_contents(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut contents, &mut in_flight);
+                    spawn_flush_trees(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut trees, &mut in_flight);
+                    spawn_flush_filenodes(&client, &logger, &ctx, &repo_blobstore, &counts, &semaphore, &mut filenodes, &mut in_flight);
+                    spawn_flush_changesets(&client, &logger, &counts, &semaphore, &mut changesets, &mut in_flight);
+                }
+            }
+            // Reap finished flushes so `in_flight` doesn't grow without bound.
+            in_flight.retain(|handle: &mononoke::JoinHandle<()>| !handle.is_finished());
+        }
+
+        for handle in in_flight.drain(..) {
+            let _ = handle.await;
+        }
+    });
+
+    handle
+}
+
+fn spawn_flush_contents(
+    client: &Client,
+    logger: &Logger,
+    ctx: &CoreContext,
+    repo_blobstore: &RepoBlobstore,
+    counts: &Arc<EntryUploadCounts>,
+    semaphore: &Arc<Semaphore>,
+    batch: &mut Vec<(AnyFileContentId, FileContents)>,
+    in_flight: &mut Vec<tokio::task::JoinHandle<()>>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let batch = std::mem::take(batch);
+    let client = client.clone();
+    let logger = logger.clone();
+    let ctx = ctx.clone();
+    let repo_blobstore = repo_blobstore.clone();
+    let counts = counts.clone();
+    let semaphore = semaphore.clone();
+    in_flight.push(tokio::spawn(async move {
+        let _permit = semaphore.acquire_owned().await;
+        let len = batch.len() as u64;
+        if let Err(e) = do_upload_contents(&client, &logger, &ctx, &repo_blobstore, batch).await {
+            tracing::error!("enqueue_entry: contents flush failed: {:?}", e);
+            return;
+        }
+        counts.contents.fetch_add(len, Ordering::Relaxed);
+    }));
+}
+
+fn spawn_flush_trees(
+    client: &Client,
+    logger: &Logger,
+    ctx: &CoreContext,
+    repo_blobstore: &RepoBlobstore,
+    counts: &Arc<EntryUploadCounts>,
+    semaphore: &Arc<Semaphore>,
+    batch: &mut Vec<HgManifestId>,
+    in_flight: &mut Vec<tokio::task::JoinHandle<()>>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let batch = std::mem::take(batch);
+    let client = client.clone();
+    let logger = logger.clone();
+    let ctx = ctx.clone();
+    let repo_blobstore = repo_blobstore.clone();
+    let counts = counts.clone();
+    let semaphore = semaphore.clone();
+    in_flight.push(tokio::spawn(async move {
+        let _permit = semaphore.acquire_owned().await;
+        let len = batch.len() as u64;
+        if let Err(e) = do_upload_trees(&client, &logger, &ctx, &repo_blobstore, batch).await {
+            tracing::error!("enqueue_entry: trees flush failed: {:?}", e);
+            return;
+        }
+        counts.trees.fetch_add(len, Ordering::Relaxed);
+    }));
+}
+
+fn spawn_flush_filenodes(
+    client: &Client,
+    logger: &Logger,
+    ctx: &CoreContext,
+    repo_blobstore: &RepoBlobstore,
+    counts: &Arc<EntryUploadCounts>,
+    semaphore: &Arc<Semaphore>,
+    batch: &mut Vec<HgFileNodeId>,
+    in_flight: &mut Vec<tokio::task::JoinHandle<()>>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let batch = std::mem::take(batch);
+    let client = client.clone();
+    let logger = logger.clone();
+    let ctx = ctx.clone();
+    let repo_blobstore = repo_blobstore.clone();
+    let counts = counts.clone();
+    let semaphore = semaphore.clone();
+    in_flight.push(tokio::spawn(async move {
+        let _permit = semaphore.acquire_owned().await;
+        let len = batch.len() as u64;
+        if let Err(e) = do_upload_filenodes(&client, &logger, &ctx, &repo_blobstore, batch).await {
+            tracing::error!("enqueue_entry: filenodes flush failed: {:?}", e);
+            return;
+        }
+        counts.filenodes.fetch_add(len, Ordering::Relaxed);
+    }));
+}
+
+fn spawn_flush_changesets(
+    client: &Client,
+    logger: &Logger,
+    counts: &Arc<EntryUploadCounts>,
+    semaphore: &Arc<Semaphore>,
+    batch: &mut Vec<HgBlobChangeset>,
+    in_flight: &mut Vec<tokio::task::JoinHandle<()>>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let batch = std::mem::take(batch);
+    let client = client.clone();
+    let logger = logger.clone();
+    let counts = counts.clone();
+    let semaphore = semaphore.clone();
+    in_flight.push(tokio::spawn(async move {
+        let _permit = semaphore.acquire_owned().await;
+        let len = batch.len() as u64;
+        if let Err(e) = do_upload_hg_changeset(&client, &logger, batch).await {
+            tracing::error!("enqueue_entry: changesets flush failed: {:?}", e);
+            return;
+        }
+        counts.changesets.fetch_add(len, Ordering::Relaxed);
+    }));
+}
+
 #[allow(dead_code)]
 pub struct EdenapiSender {
     client: Client,
     logger: Logger,
     ctx: CoreContext,
     repo_blobstore: RepoBlobstore,
+    negotiated: NegotiatedCapabilities,
+    pipeline: EntryPipelineHandle,
 }
 
 impl EdenapiSender {
@@ -80,107 +424,404 @@ impl EdenapiSender {
 
         let res = client.health().await;
         info!(logger, "Health check outcome: {:?}", res);
+
+        let negotiated = Self::handshake(&client, &reponame, &logger).await?;
+
+        let pipeline = spawn_entry_pipeline(
+            client.clone(),
+            logger.clone(),
+            ctx.clone(),
+            repo_blobstore.clone(),
+            EntryPipelineConfig::default(),
+        );
+
         Ok(Self {
             client,
             logger,
             ctx,
             repo_blobstore,
+            negotiated,
+            pipeline,
         })
     }
-}
 
-#[async_trait]
-impl ModernSyncSender for EdenapiSender {
-    async fn enqueue_entry(&self, _entry: Entry) -> Result<()> {
-        // TODO: implement using mpsc channels
-        Ok(())
+    /// Waits until every `Entry` handed to `enqueue_entry` so far, including
+    /// whatever is sitting in a partially-filled batch, has gone through an
+    /// upload attempt. Failed batches are logged and dropped, not reported.
+    pub async fn flush(&self) -> Result<()> {
+        self.pipeline.flush().await
     }
 
-    async fn upload_contents(&self, contents: Vec<(AnyFileContentId, FileContents)>) -> Result<()> {
+    /// Per-category counts of entries the `enqueue_entry` pipeline has
+    /// uploaded so far, for progress reporting.
+    pub fn upload_counts(&self) -> EntryUploadCountsSnapshot {
+        self.pipeline.counts()
+    }
+
+    /// Exchange protocol version and capabilities with the remote before any
+    /// manager starts draining its channel, so an incompatible receiver is
+    /// rejected up front rather than mid-stream.
+    async fn handshake(
+        client: &Client,
+        reponame: &str,
+        logger: &Logger,
+    ) -> Result<NegotiatedCapabilities> {
+        let _ = client.health().await;
+
+        // The receiver advertises the protocol versions and feature
+        // capabilities it understands as plain capability strings (a
+        // version is reported as `modern-sync-protocol-v<N>`); an older
+        // receiver that predates this endpoint, or one that can't be
+        // reached, is treated as speaking the baseline protocol with none
+        // of the optional capabilities, so we downgrade to it rather than
+        // failing outright.
+        let remote_capabilities: HashSet<String> = client
+            .capabilities(reponame.to_string())
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+
+        let remote_version = remote_capabilities
+            .iter()
+            .filter_map(|c| c.strip_prefix("modern-sync-protocol-v"))
+            .filter_map(|v| v.parse::<u32>().ok())
+            .max()
+            .unwrap_or(MIN_SUPPORTED_PROTOCOL_VERSION);
+
+        if remote_version < MIN_SUPPORTED_PROTOCOL_VERSION {
+            return Err(anyhow::anyhow!(
+                "Remote protocol version {} is below the minimum supported version {}",
+                remote_version,
+                MIN_SUPPORTED_PROTOCOL_VERSION
+            ));
+        }
+
+        // Speak the lower of the two versions: we can't rely on features a
+        // receiver that hasn't caught up to us yet wouldn't understand.
+        let protocol_version = remote_version.min(PROTOCOL_VERSION);
+
+        // Only the capabilities both sides actually advertise are usable;
+        // unconditionally trusting every entry of `KNOWN_CAPABILITIES`
+        // would serve a feature (e.g. zstd-content) to a receiver that
+        // never claimed to support it.
+        let capabilities: HashSet<String> = KNOWN_CAPABILITIES
+            .iter()
+            .map(|s| s.to_string())
+            .filter(|c| remote_capabilities.contains(c))
+            .collect();
+
         info!(
-            &self.logger,
-            "Uploading contents: {:?}",
-            contents
-                .clone()
-                .into_iter()
-                .map(|(first, _)| first)
-                .collect::<Vec<_>>()
+            logger,
+            "Negotiated protocol version {} with capabilities {:?}", protocol_version, capabilities
         );
 
-        for (id, blob) in contents {
-            match blob {
-                FileContents::Bytes(bytes) => {
-                    info!(&self.logger, "Uploading bytes: {:?}", bytes);
-                    let response = self
-                        .client
-                        .process_files_upload(vec![(id, bytes.into())], None, None)
-                        .await?;
-                    info!(
-                        &self.logger,
-                        "Upload response: {:?}",
-                        response.entries.try_collect::<Vec<_>>().await?
-                    );
-                }
-                _ => (),
+        Ok(NegotiatedCapabilities {
+            protocol_version,
+            capabilities,
+        })
+    }
+
+    pub fn negotiated(&self) -> &NegotiatedCapabilities {
+        &self.negotiated
+    }
+}
+
+/// Segment size for chunked content uploads: large enough to keep
+/// round-trip overhead low, small enough that a transient failure only
+/// costs re-sending a few MB rather than an entire blob.
+const CONTENT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// How many segments (across all blobs in a batch) may be uploading at once.
+const MAX_CONCURRENT_CHUNK_UPLOADS: usize = 8;
+
+const CHUNK_UPLOAD_MAX_ATTEMPTS: u32 = 5;
+const CHUNK_UPLOAD_BASE_DELAY: Duration = Duration::from_millis(100);
+const CHUNK_UPLOAD_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// One fixed-size slice of a blob queued for upload, keyed by the whole
+/// blob's content id. Segmenting a large blob lets a failed chunk be
+/// retried on its own instead of re-sending the whole file.
+#[derive(Clone)]
+struct ContentSegment {
+    id: AnyFileContentId,
+    index: u32,
+    total: u32,
+    data: bytes::Bytes,
+}
+
+fn split_into_segments(
+    id: AnyFileContentId,
+    data: bytes::Bytes,
+    max_segment_size: usize,
+) -> Vec<ContentSegment> {
+    if data.is_empty() {
+        return vec![ContentSegment {
+            id,
+            index: 0,
+            total: 1,
+            data,
+        }];
+    }
+
+    let total = data.len().div_ceil(max_segment_size) as u32;
+    (0..total)
+        .map(|index| {
+            let start = index as usize * max_segment_size;
+            let end = (start + max_segment_size).min(data.len());
+            ContentSegment {
+                id: id.clone(),
+                index,
+                total,
+                data: data.slice(start..end),
+            }
+        })
+        .collect()
+}
+
+/// Resolves a `FileContents` to its full bytes, fetching `Chunked` content
+/// from the blobstore chunk-by-chunk. `Bytes` contents are already fully
+/// materialized and are returned as-is.
+async fn materialize_file_contents(
+    ctx: &CoreContext,
+    repo_blobstore: &RepoBlobstore,
+    id: &AnyFileContentId,
+    contents: FileContents,
+) -> Result<bytes::Bytes> {
+    match contents {
+        FileContents::Bytes(bytes) => Ok(bytes.into()),
+        FileContents::Chunked(chunked) => {
+            let mut out = Vec::new();
+            for chunk in chunked.into_chunks() {
+                let key = chunk.chunk_id().blobstore_key();
+                let data = repo_blobstore
+                    .get(ctx, &key)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("missing content chunk {} for {:?}", key, id))?;
+                out.extend_from_slice(data.as_raw_bytes());
             }
+            Ok(bytes::Bytes::from(out))
         }
+    }
+}
 
-        Ok(())
+/// Asks the server which of `segments` it already has, by content id, so a
+/// resumed sync doesn't re-send blobs the server already holds. Since all
+/// segments of a blob share its id, this is decided per blob: a blob the
+/// server doesn't report as present has all of its segments sent again,
+/// including any that an earlier run uploaded. Best-effort:
+/// if the presence check itself fails, every segment is treated as missing
+/// rather than failing the whole upload.
+async fn filter_present_segments(
+    client: &Client,
+    logger: &Logger,
+    segments: Vec<ContentSegment>,
+) -> Vec<ContentSegment> {
+    let ids: Vec<AnyFileContentId> = segments.iter().map(|s| s.id.clone()).collect();
+    match client.lookup_batch(ids, None, None).await {
+        Ok(present) => {
+            let present: HashSet<AnyFileContentId> = present.into_iter().collect();
+            segments
+                .into_iter()
+                .filter(|segment| !present.contains(&segment.id))
+                .collect()
+        }
+        Err(e) => {
+            info!(
+                logger,
+                "Presence check failed, uploading all segments: {:?}", e
+            );
+            segments
+        }
+    }
+}
+
+/// Payload actually put on the wire for `segment`. A blob that fit in one
+/// segment (the common case) is sent exactly as before: the id maps to the
+/// blob's full, untagged bytes. A blob split into more than one segment
+/// shares its `id` across all of them (so the presence check above still
+/// answers "does the receiver have this content" once per blob, not once
+/// per chunk), which means the chunk's position has to travel with the
+/// bytes themselves rather than the id — so it's prefixed with `index` and
+/// `total`, each a little-endian `u32`, letting the receiver tell segments
+/// of the same blob apart and reassemble them in order.
+fn segment_payload(segment: &ContentSegment) -> bytes::Bytes {
+    if segment.total <= 1 {
+        return segment.data.clone();
     }
+    let mut framed = Vec::with_capacity(8 + segment.data.len());
+    framed.extend_from_slice(&segment.index.to_le_bytes());
+    framed.extend_from_slice(&segment.total.to_le_bytes());
+    framed.extend_from_slice(&segment.data);
+    bytes::Bytes::from(framed)
+}
 
-    async fn upload_trees(&self, trees: Vec<HgManifestId>) -> Result<()> {
-        let entries = stream::iter(trees)
-            .map(|mf_id| {
-                let ctx = self.ctx.clone();
-                let repo_blobstore = self.repo_blobstore.clone();
-                async move { util::from_tree_to_entry(mf_id, &ctx, &repo_blobstore).await }
-            })
-            .buffer_unordered(10)
-            .try_collect::<Vec<_>>()
-            .await?;
+/// Uploads a single segment, retrying independently of the rest of the
+/// batch on a transient failure. Mirrors the exponential-backoff shape used
+/// elsewhere in this codebase (see `edenfs_error::RetryStrategyPolicy`)
+/// without pulling that crate in across workspaces.
+async fn upload_segment_with_retry(client: &Client, logger: &Logger, segment: ContentSegment) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        let result = client
+            .process_files_upload(
+                vec![(segment.id.clone(), segment_payload(&segment).into())],
+                None,
+                None,
+            )
+            .await;
 
-        let res = self.client.upload_trees_batch(entries).await?;
-        info!(
-            &self.logger,
-            "Upload tree response: {:?}",
-            res.entries.try_collect::<Vec<_>>().await?
-        );
-        Ok(())
+        match result {
+            Ok(response) => {
+                response.entries.try_collect::<Vec<_>>().await?;
+                return Ok(());
+            }
+            Err(e) if attempt + 1 < CHUNK_UPLOAD_MAX_ATTEMPTS => {
+                attempt += 1;
+                let delay = CHUNK_UPLOAD_BASE_DELAY
+                    .saturating_mul(1u32.checked_shl(attempt).unwrap_or(u32::MAX))
+                    .min(CHUNK_UPLOAD_MAX_DELAY);
+                info!(
+                    logger,
+                    "Segment {}/{} of {:?} failed (attempt {}), retrying in {:?}: {:?}",
+                    segment.index + 1,
+                    segment.total,
+                    segment.id,
+                    attempt,
+                    delay,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
     }
+}
 
-    async fn upload_filenodes(&self, fn_ids: Vec<HgFileNodeId>) -> Result<()> {
-        let filenodes = stream::iter(fn_ids)
-            .map(|file_id| {
-                let ctx = self.ctx.clone();
-                let repo_blobstore = self.repo_blobstore.clone();
-                async move { util::from_id_to_filenode(file_id, &ctx, &repo_blobstore).await }
-            })
-            .buffer_unordered(10)
-            .try_collect::<Vec<_>>()
-            .await?;
+async fn do_upload_contents(
+    client: &Client,
+    logger: &Logger,
+    ctx: &CoreContext,
+    repo_blobstore: &RepoBlobstore,
+    contents: Vec<(AnyFileContentId, FileContents)>,
+) -> Result<()> {
+    info!(
+        logger,
+        "Uploading contents: {:?}",
+        contents
+            .iter()
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>()
+    );
 
-        let res = self.client.upload_filenodes_batch(filenodes).await?;
-        info!(
-            &self.logger,
-            "Upload filenodes response: {:?}",
-            res.entries.try_collect::<Vec<_>>().await?
-        );
-        Ok(())
+    let mut segments = Vec::new();
+    for (id, blob) in contents {
+        let data = materialize_file_contents(ctx, repo_blobstore, &id, blob).await?;
+        segments.extend(split_into_segments(id, data, CONTENT_CHUNK_SIZE));
     }
 
-    async fn upload_hg_changeset(&self, hg_css: Vec<HgBlobChangeset>) -> Result<()> {
-        let entries = stream::iter(hg_css)
-            .map(util::to_upload_hg_changeset)
-            .try_collect::<Vec<_>>()
-            .await?;
+    let segments = filter_present_segments(client, logger, segments).await;
+    if segments.is_empty() {
+        return Ok(());
+    }
 
-        let res = self.client.upload_changesets(entries, vec![]).await?;
-        info!(
-            &self.logger,
-            "Upload hg changeset response: {:?}",
-            res.entries.try_collect::<Vec<_>>().await?
-        );
-        Ok(())
+    stream::iter(segments)
+        .map(|segment| upload_segment_with_retry(client, logger, segment))
+        .buffer_unordered(MAX_CONCURRENT_CHUNK_UPLOADS)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    Ok(())
+}
+
+async fn do_upload_trees(
+    client: &Client,
+    logger: &Logger,
+    ctx: &CoreContext,
+    repo_blobstore: &RepoBlobstore,
+    trees: Vec<HgManifestId>,
+) -> Result<()> {
+    let entries = stream::iter(trees)
+        .map(|mf_id| async move { util::from_tree_to_entry(mf_id, ctx, repo_blobstore).await })
+        .buffer_unordered(10)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let res = client.upload_trees_batch(entries).await?;
+    info!(
+        logger,
+        "Upload tree response: {:?}",
+        res.entries.try_collect::<Vec<_>>().await?
+    );
+    Ok(())
+}
+
+async fn do_upload_filenodes(
+    client: &Client,
+    logger: &Logger,
+    ctx: &CoreContext,
+    repo_blobstore: &RepoBlobstore,
+    fn_ids: Vec<HgFileNodeId>,
+) -> Result<()> {
+    let filenodes = stream::iter(fn_ids)
+        .map(|file_id| async move { util::from_id_to_filenode(file_id, ctx, repo_blobstore).await })
+        .buffer_unordered(10)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let res = client.upload_filenodes_batch(filenodes).await?;
+    info!(
+        logger,
+        "Upload filenodes response: {:?}",
+        res.entries.try_collect::<Vec<_>>().await?
+    );
+    Ok(())
+}
+
+async fn do_upload_hg_changeset(
+    client: &Client,
+    logger: &Logger,
+    hg_css: Vec<HgBlobChangeset>,
+) -> Result<()> {
+    let entries = stream::iter(hg_css)
+        .map(util::to_upload_hg_changeset)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let res = client.upload_changesets(entries, vec![]).await?;
+    info!(
+        logger,
+        "Upload hg changeset response: {:?}",
+        res.entries.try_collect::<Vec<_>>().await?
+    );
+    Ok(())
+}
+
+#[async_trait]
+impl ModernSyncSender for EdenapiSender {
+    /// Hands `entry` to the background pipeline spawned in `new()`, which
+    /// coalesces it with others of its category into a batch and uploads the
+    /// batch once it's full or `EntryPipelineConfig::flush_interval` elapses.
+    /// Awaiting the bounded channel send is the backpressure: once the
+    /// pipeline falls behind, this call blocks until it catches up.
+    async fn enqueue_entry(&self, entry: Entry) -> Result<()> {
+        self.pipeline.send(entry).await
+    }
+
+    async fn upload_contents(&self, contents: Vec<(AnyFileContentId, FileContents)>) -> Result<()> {
+        do_upload_contents(&self.client, &self.logger, &self.ctx, &self.repo_blobstore, contents).await
+    }
+
+    async fn upload_trees(&self, trees: Vec<HgManifestId>) -> Result<()> {
+        do_upload_trees(&self.client, &self.logger, &self.ctx, &self.repo_blobstore, trees).await
+    }
+
+    async fn upload_filenodes(&self, fn_ids: Vec<HgFileNodeId>) -> Result<()> {
+        do_upload_filenodes(&self.client, &self.logger, &self.ctx, &self.repo_blobstore, fn_ids).await
+    }
+
+    async fn upload_hg_changeset(&self, hg_css: Vec<HgBlobChangeset>) -> Result<()> {
+        do_upload_hg_changeset(&self.client, &self.logger, hg_css).await
     }
 
     async fn set_bookmark(
@@ -8,6 +8,7 @@
 use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
@@ -26,6 +27,10 @@ use mutable_counters::MutableCounters;
 use repo_blobstore::RepoBlobstore;
 use tokio::sync::mpsc;
 
+use crate::checkpoint::Checkpoint;
+use crate::checkpoint::CheckpointStore;
+use crate::maintenance::MaintenanceScheduler;
+use crate::maintenance::ScheduledJob;
 use crate::sender::edenapi::EdenapiSender;
 use crate::sender::manager::changeset::ChangesetManager;
 use crate::sender::manager::content::ContentManager;
@@ -63,12 +68,137 @@ const MAX_CONTENT_BATCH_SIZE: usize = 300;
 const MAX_FILENODES_BATCH_SIZE: usize = 500;
 const MAX_BLOB_BYTES: u64 = 10 * 10 * 1024 * 1024; // 100 MB
 
+// Default zstd compression level for content batches: fast with a good ratio.
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Runtime-tunable knobs for `SendManager`'s channels: capacities, batch
+/// limits, the blob byte cap, and flush intervals. `Default` preserves the
+/// values this module used to hardcode. `SendManager::new` applies the four
+/// channel capacities and the content and tree batch sizes and flush
+/// intervals; the changeset and filenode batch sizes and flush intervals and
+/// `max_blob_bytes` are carried here but not read by any manager.
+#[derive(Clone, Debug)]
+pub struct SendManagerConfig {
+    pub content_channel_size: usize,
+    pub files_channel_size: usize,
+    pub trees_channel_size: usize,
+    pub changeset_channel_size: usize,
+
+    pub changesets_flush_interval: Duration,
+    pub trees_flush_interval: Duration,
+    pub filenodes_flush_interval: Duration,
+    pub contents_flush_interval: Duration,
+
+    pub max_changeset_batch_size: usize,
+    pub max_trees_batch_size: usize,
+    pub max_content_batch_size: usize,
+    pub max_filenodes_batch_size: usize,
+    pub max_blob_bytes: u64,
+}
+
+impl Default for SendManagerConfig {
+    fn default() -> Self {
+        Self {
+            content_channel_size: CONTENT_CHANNEL_SIZE,
+            files_channel_size: FILES_CHANNEL_SIZE,
+            trees_channel_size: TREES_CHANNEL_SIZE,
+            changeset_channel_size: CHANGESET_CHANNEL_SIZE,
+
+            changesets_flush_interval: CHANGESETS_FLUSH_INTERVAL,
+            trees_flush_interval: TREES_FLUSH_INTERVAL,
+            filenodes_flush_interval: FILENODES_FLUSH_INTERVAL,
+            contents_flush_interval: CONTENTS_FLUSH_INTERVAL,
+
+            max_changeset_batch_size: MAX_CHANGESET_BATCH_SIZE,
+            max_trees_batch_size: MAX_TREES_BATCH_SIZE,
+            max_content_batch_size: MAX_CONTENT_BATCH_SIZE,
+            max_filenodes_batch_size: MAX_FILENODES_BATCH_SIZE,
+            max_blob_bytes: MAX_BLOB_BYTES,
+        }
+    }
+}
+
+/// Codec for framing a batched content payload. `frame` tags each frame
+/// with a one-byte codec id followed by the uncompressed length, so a
+/// receiver can pick the right decoder even if the two sides negotiated
+/// different defaults. `ContentManager` does not call `frame`: contents are
+/// uploaded uncompressed whichever codec is selected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Send the batch unmodified. Used when talking to a receiver that
+    /// hasn't negotiated zstd support.
+    None,
+    /// zstd-compress the concatenated batch bytes at the given level.
+    Zstd { level: i32 },
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::Zstd {
+            level: DEFAULT_ZSTD_LEVEL,
+        }
+    }
+}
+
+impl Compression {
+    pub(crate) fn codec_id(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Zstd { .. } => 1,
+        }
+    }
+
+    /// Frame `bytes` for the wire: a one-byte codec id, the uncompressed
+    /// length as a little-endian u64, and then the (possibly compressed)
+    /// payload.
+    pub(crate) fn frame(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let uncompressed_len = bytes.len() as u64;
+        let payload = match self {
+            Compression::None => bytes.to_vec(),
+            Compression::Zstd { level } => zstd::stream::encode_all(bytes, *level)?,
+        };
+        let mut framed = Vec::with_capacity(1 + 8 + payload.len());
+        framed.push(self.codec_id());
+        framed.extend_from_slice(&uncompressed_len.to_le_bytes());
+        framed.extend_from_slice(&payload);
+        Ok(framed)
+    }
+}
+
 #[derive(Clone)]
 pub struct SendManager {
     content_sender: mpsc::Sender<ContentMessage>,
     files_sender: mpsc::Sender<FileMessage>,
     trees_sender: mpsc::Sender<TreeMessage>,
     changeset_sender: mpsc::Sender<ChangesetMessage>,
+    worker_status: Arc<WorkerStatuses>,
+    // The checkpoint resumption is gated on at construction time (if any),
+    // and the store to persist new ones to. Exposed via `resume_from` /
+    // `save_checkpoint` so the tailer driving `ChangesetMessage::
+    // CheckpointInEntry`/`FinishEntry` can skip past already-synced entries
+    // and checkpoint its progress as it goes.
+    resume_from: Option<Arc<Checkpoint>>,
+    checkpoint_store: Arc<CheckpointStore>,
+}
+
+/// One `WorkerStatus` per channel, so a supervisor can tell which of the
+/// four (if any) is stuck rather than just that the whole sync is slow.
+pub struct WorkerStatuses {
+    pub content: Arc<WorkerStatus>,
+    pub files: Arc<WorkerStatus>,
+    pub trees: Arc<WorkerStatus>,
+    pub changesets: Arc<WorkerStatus>,
+}
+
+impl Default for WorkerStatuses {
+    fn default() -> Self {
+        Self {
+            content: Arc::new(WorkerStatus::default()),
+            files: Arc::new(WorkerStatus::default()),
+            trees: Arc::new(WorkerStatus::default()),
+            changesets: Arc::new(WorkerStatus::default()),
+        }
+    }
 }
 
 pub enum ContentMessage {
@@ -119,6 +249,74 @@ pub enum ChangesetMessage {
     Log((String, Option<i64>)),
 }
 
+/// One channel's worth of atomic transfer counters. Updated as payloads are
+/// handed to the `EdenapiSender` (not when they're enqueued), so these
+/// reflect true transfer progress rather than how far ahead the producer
+/// side has gotten.
+#[derive(Default)]
+pub struct ChannelProgress {
+    pub bytes_sent: AtomicU64,
+    pub objects_sent: AtomicU64,
+    pub in_flight_bytes: AtomicU64,
+}
+
+impl ChannelProgress {
+    pub(crate) fn record_enqueued(&self, bytes: u64) {
+        self.in_flight_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_sent(&self, bytes: u64, objects: u64) {
+        self.in_flight_bytes.fetch_sub(bytes, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+        self.objects_sent.fetch_add(objects, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> ChannelProgressSnapshot {
+        ChannelProgressSnapshot {
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            objects_sent: self.objects_sent.load(Ordering::Relaxed),
+            in_flight_bytes: self.in_flight_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChannelProgressSnapshot {
+    pub bytes_sent: u64,
+    pub objects_sent: u64,
+    pub in_flight_bytes: u64,
+}
+
+/// Shared, lock-free progress counters for the four sync channels. Cheap to
+/// update on the hot path (a handful of atomic ops per flush) and cheap to
+/// snapshot periodically for reporting.
+#[derive(Default)]
+pub struct SyncProgress {
+    pub content: ChannelProgress,
+    pub files: ChannelProgress,
+    pub trees: ChannelProgress,
+    pub changesets: ChannelProgress,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProgressEvent {
+    pub content: ChannelProgressSnapshot,
+    pub files: ChannelProgressSnapshot,
+    pub trees: ChannelProgressSnapshot,
+    pub changesets: ChannelProgressSnapshot,
+}
+
+impl SyncProgress {
+    fn snapshot(&self) -> ProgressEvent {
+        ProgressEvent {
+            content: self.content.snapshot(),
+            files: self.files.snapshot(),
+            trees: self.trees.snapshot(),
+            changesets: self.changesets.snapshot(),
+        }
+    }
+}
+
 pub struct BookmarkInfo {
     pub name: String,
     pub from_cs_id: Option<HgChangesetId>,
@@ -134,43 +332,103 @@ impl SendManager {
         exit_file: PathBuf,
         mc: Arc<dyn MutableCounters + Send + Sync>,
         cancellation_requested: Arc<AtomicBool>,
+        compression: Compression,
+        progress_sender: Option<mpsc::Sender<ProgressEvent>>,
+        config: SendManagerConfig,
+        checkpoint_path: PathBuf,
+        negotiated_protocol_version: u32,
     ) -> Self {
+        let progress = Arc::new(SyncProgress::default());
+        let worker_status = Arc::new(WorkerStatuses::default());
+
+        // Load and validate any previously persisted checkpoint before the
+        // managers start draining, so an incompatible resume is rejected up
+        // front rather than failing mid-stream.
+        let checkpoint_store = CheckpointStore::new(checkpoint_path);
+        let resume_from: Option<Checkpoint> = match checkpoint_store.load() {
+            Ok(Some(checkpoint)) => {
+                match CheckpointStore::validate_resume(&checkpoint, negotiated_protocol_version) {
+                    Ok(()) => Some(checkpoint),
+                    Err(e) => {
+                        tracing::warn!("Refusing to resume from incompatible checkpoint: {:?}", e);
+                        None
+                    }
+                }
+            }
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!("Failed to load checkpoint, starting fresh: {:?}", e);
+                None
+            }
+        };
+        if let Some(checkpoint) = &resume_from {
+            tracing::info!(
+                "Resuming from entry {} position {}",
+                checkpoint.entry_id,
+                checkpoint.position_in_entry
+            );
+        }
+        let resume_from = resume_from.map(Arc::new);
+        let checkpoint_store = Arc::new(checkpoint_store);
+
         // Create channel for receiving content
-        let (content_sender, content_recv) = mpsc::channel(CONTENT_CHANNEL_SIZE);
-        ContentManager::new(content_recv, repo_blobstore).start(
+        let (content_sender, content_recv) = mpsc::channel(config.content_channel_size);
+        ContentManager::new(content_recv, repo_blobstore, compression, &config).start(
             ctx.clone(),
             reponame.clone(),
             external_sender.clone(),
             cancellation_requested.clone(),
+            progress.clone(),
+            worker_status.content.clone(),
         );
 
         // Create channel for receiving files
-        let (files_sender, files_recv) = mpsc::channel(FILES_CHANNEL_SIZE);
+        let (files_sender, files_recv) = mpsc::channel(config.files_channel_size);
         FilenodeManager::new(files_recv).start(
             ctx.clone(),
             reponame.clone(),
             external_sender.clone(),
             cancellation_requested.clone(),
+            progress.clone(),
+            worker_status.files.clone(),
         );
 
         // Create channel for receiving trees
-        let (trees_sender, trees_recv) = mpsc::channel(TREES_CHANNEL_SIZE);
-        TreeManager::new(trees_recv).start(
+        let (trees_sender, trees_recv) = mpsc::channel(config.trees_channel_size);
+        TreeManager::new(trees_recv, &config).start(
             ctx.clone(),
             reponame.clone(),
             external_sender.clone(),
             cancellation_requested.clone(),
+            progress.clone(),
+            worker_status.trees.clone(),
         );
 
         // Create channel for receiving changesets
-        let (changeset_sender, changeset_recv) = mpsc::channel(CHANGESET_CHANNEL_SIZE);
+        let (changeset_sender, changeset_recv) = mpsc::channel(config.changeset_channel_size);
         ChangesetManager::new(changeset_recv, mc).start(
             ctx.clone(),
             reponame.clone(),
             external_sender.clone(),
             cancellation_requested.clone(),
+            progress.clone(),
+            worker_status.changesets.clone(),
         );
 
+        if let Some(progress_sender) = progress_sender {
+            let progress = progress.clone();
+            let cancellation_requested = cancellation_requested.clone();
+            mononoke::spawn_task(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(1));
+                while !cancellation_requested.load(Ordering::Relaxed) {
+                    interval.tick().await;
+                    if progress_sender.send(progress.snapshot()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
         mononoke::spawn_task(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(5));
             loop {
@@ -183,14 +441,78 @@ impl SendManager {
             }
         });
 
+        // Queue-health summary: logs each channel's worker state and
+        // in-flight bytes periodically, independent of whatever cadence the
+        // inbound traffic happens to produce.
+        let queue_health_job = {
+            let worker_status = worker_status.clone();
+            let progress = progress.clone();
+            ScheduledJob {
+                name: "queue_health_summary",
+                interval: Duration::from_secs(30),
+                run: Box::new(move || {
+                    let worker_status = worker_status.clone();
+                    let progress = progress.clone();
+                    Box::pin(async move {
+                        let snapshot = progress.snapshot();
+                        tracing::info!(
+                            "Queue health: content={:?}/{:?} files={:?}/{:?} trees={:?}/{:?} changesets={:?}/{:?}",
+                            worker_status.content.get(),
+                            snapshot.content,
+                            worker_status.files.get(),
+                            snapshot.files,
+                            worker_status.trees.get(),
+                            snapshot.trees,
+                            worker_status.changesets.get(),
+                            snapshot.changesets,
+                        );
+                        Ok(())
+                    })
+                }),
+            }
+        };
+        MaintenanceScheduler::new(reponame.clone(), ctx.logger().clone(), vec![queue_health_job])
+            .start(cancellation_requested.clone());
+
         Self {
             content_sender,
             files_sender,
             trees_sender,
             changeset_sender,
+            worker_status,
+            resume_from,
+            checkpoint_store,
         }
     }
 
+    /// The checkpoint this `SendManager` was constructed to resume from, if
+    /// a valid one was found at `checkpoint_path`. A caller driving the BUL
+    /// tail loop should skip re-sending anything at or before
+    /// `(entry_id, position_in_entry)` before enqueueing work.
+    pub fn resume_from(&self) -> Option<&Checkpoint> {
+        self.resume_from.as_deref()
+    }
+
+    /// Persists `checkpoint` so a future run can resume from it via
+    /// `resume_from`. Intended to be called on a cadence (e.g. once per
+    /// flushed `ChangesetMessage::CheckpointInEntry`/`FinishEntry`) by the
+    /// code driving this `SendManager`.
+    pub fn save_checkpoint(&self, checkpoint: &Checkpoint) -> Result<()> {
+        self.checkpoint_store.save(checkpoint)
+    }
+
+    /// Current lifecycle state of each of the four channel workers, so
+    /// operators/tests can tell which one (if any) is stuck rather than just
+    /// that the whole sync looks slow.
+    pub fn worker_states(&self) -> (WorkerState, WorkerState, WorkerState, WorkerState) {
+        (
+            self.worker_status.content.get(),
+            self.worker_status.files.get(),
+            self.worker_status.trees.get(),
+            self.worker_status.changesets.get(),
+        )
+    }
+
     pub async fn send_content(&self, content_msg: ContentMessage) -> Result<()> {
         self.content_sender
             .send(content_msg)
@@ -248,6 +570,45 @@ impl SendManager {
     }
 }
 
+/// Lifecycle state of a `Manager`'s background worker, as observed by a
+/// supervisor. `Busy` while handling a received message or flushing a batch,
+/// `Idle` while blocked waiting on its channel or the flush timer, `Done`
+/// once the worker has exited (its channel closed, cancellation was
+/// requested, or a flush failed).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum WorkerState {
+    Idle = 0,
+    Busy = 1,
+    Done = 2,
+}
+
+impl WorkerState {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => WorkerState::Busy,
+            2 => WorkerState::Done,
+            _ => WorkerState::Idle,
+        }
+    }
+}
+
+/// Shared, lock-free handle a worker uses to publish its `WorkerState` and a
+/// supervisor uses to observe it, so `SendManager` can tell which of its four
+/// channels (if any) is stuck rather than just that the whole sync is slow.
+#[derive(Default)]
+pub struct WorkerStatus(AtomicU64);
+
+impl WorkerStatus {
+    pub(crate) fn set(&self, state: WorkerState) {
+        self.0.store(state as u64, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> WorkerState {
+        WorkerState::from_u8(self.0.load(Ordering::Relaxed) as u8)
+    }
+}
+
 trait Manager {
     fn start(
         self,
@@ -255,5 +616,7 @@ trait Manager {
         reponame: String,
         external_sender: Arc<dyn EdenapiSender + Send + Sync>,
         cancellation_requested: Arc<AtomicBool>,
+        progress: Arc<SyncProgress>,
+        status: Arc<WorkerStatus>,
     );
 }
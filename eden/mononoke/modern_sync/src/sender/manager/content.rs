@@ -0,0 +1,188 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use context::CoreContext;
+use edenapi_types::AnyFileContentId;
+use futures::channel::oneshot;
+use mononoke_types::ContentId;
+use mononoke_types::FileContents;
+use repo_blobstore::RepoBlobstore;
+use tokio::sync::mpsc;
+use tokio::time::interval;
+
+use crate::sender::edenapi::EdenapiSender;
+use crate::sender::manager::Compression;
+use crate::sender::manager::ContentMessage;
+use crate::sender::manager::Manager;
+use crate::sender::manager::SendManagerConfig;
+use crate::sender::manager::SyncProgress;
+use crate::sender::manager::WorkerState;
+use crate::sender::manager::WorkerStatus;
+
+pub(crate) struct ContentManager {
+    content_recv: mpsc::Receiver<ContentMessage>,
+    repo_blobstore: RepoBlobstore,
+    // Negotiated per `EdenapiSender::negotiated`, but not currently applied:
+    // `upload_contents` hashes each content's bytes against its declared
+    // `AnyFileContentId`, so there's no batch-level frame to compress them
+    // into without breaking that check. Kept (and threaded through from
+    // `SendManager::new`) for a future wire format that can carry it.
+    compression: Compression,
+    max_content_batch_size: usize,
+    contents_flush_interval: Duration,
+}
+
+impl ContentManager {
+    pub(crate) fn new(
+        content_recv: mpsc::Receiver<ContentMessage>,
+        repo_blobstore: RepoBlobstore,
+        compression: Compression,
+        config: &SendManagerConfig,
+    ) -> Self {
+        Self {
+            content_recv,
+            repo_blobstore,
+            compression,
+            max_content_batch_size: config.max_content_batch_size,
+            contents_flush_interval: config.contents_flush_interval,
+        }
+    }
+
+    async fn flush_contents(
+        ctx: &CoreContext,
+        repo_blobstore: &RepoBlobstore,
+        content_es: &Arc<dyn EdenapiSender + Send + Sync>,
+        _compression: Compression,
+        progress: &SyncProgress,
+        batch_contents: &mut Vec<(ContentId, u64)>,
+        batch_done_senders: &mut VecDeque<(oneshot::Sender<Result<()>>, oneshot::Sender<Result<()>>)>,
+        encountered_error: &mut Option<anyhow::Error>,
+    ) -> Result<()> {
+        if batch_contents.is_empty() && batch_done_senders.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(e) = encountered_error {
+            let msg = format!("Error processing contents: {:?}", e);
+            while let Some((files_sender, trees_sender)) = batch_done_senders.pop_front() {
+                let _ = files_sender.send(Err(anyhow::anyhow!(msg.clone())));
+                let _ = trees_sender.send(Err(anyhow::anyhow!(msg.clone())));
+            }
+            return Err(anyhow::anyhow!(msg));
+        }
+
+        if !batch_contents.is_empty() {
+            // `upload_contents` hashes each content's raw bytes against its
+            // declared `AnyFileContentId`, so a batch can't be compressed as
+            // a single opaque frame here -- `compression` isn't consumed on
+            // this path (see the field's doc comment on `ContentManager`).
+            let batch_bytes: u64 = batch_contents.iter().map(|(_, size)| *size).sum();
+            let batch_objects = batch_contents.len() as u64;
+            let contents = fetch_batch_contents(ctx, repo_blobstore, batch_contents).await?;
+            progress.content.record_enqueued(batch_bytes);
+            content_es.upload_contents(contents).await?;
+            progress.content.record_sent(batch_bytes, batch_objects);
+            batch_contents.clear();
+        }
+
+        while let Some((files_sender, trees_sender)) = batch_done_senders.pop_front() {
+            let _ = files_sender.send(Ok(()));
+            let _ = trees_sender.send(Ok(()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Fetches each content's raw bytes from `repo_blobstore` and pairs it with
+/// its `AnyFileContentId`, matching the shape `EdenapiSender::upload_contents`
+/// expects -- one entry per content rather than one entry per batch.
+async fn fetch_batch_contents(
+    ctx: &CoreContext,
+    repo_blobstore: &RepoBlobstore,
+    batch_contents: &[(ContentId, u64)],
+) -> Result<Vec<(AnyFileContentId, FileContents)>> {
+    let mut contents = Vec::with_capacity(batch_contents.len());
+    for (content_id, _size) in batch_contents {
+        let key = content_id.blobstore_key();
+        let data = repo_blobstore
+            .get(ctx, &key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing content blob {} for {:?}", key, content_id))?;
+        contents.push((
+            AnyFileContentId::ContentId(content_id.clone()),
+            FileContents::Bytes(bytes::Bytes::from(data.as_raw_bytes().to_vec())),
+        ));
+    }
+    Ok(contents)
+}
+
+impl Manager for ContentManager {
+    fn start(
+        mut self,
+        ctx: CoreContext,
+        reponame: String,
+        external_sender: Arc<dyn EdenapiSender + Send + Sync>,
+        cancellation_requested: Arc<AtomicBool>,
+        progress: Arc<SyncProgress>,
+        status: Arc<WorkerStatus>,
+    ) {
+        mononoke::spawn_task(async move {
+            let content_recv = &mut self.content_recv;
+            let repo_blobstore = self.repo_blobstore.clone();
+            let compression = self.compression;
+            let max_content_batch_size = self.max_content_batch_size;
+
+            let mut encountered_error: Option<anyhow::Error> = None;
+            let mut batch_contents: Vec<(ContentId, u64)> = Vec::new();
+            let mut batch_done_senders = VecDeque::new();
+            let mut timer = interval(self.contents_flush_interval);
+
+            while !cancellation_requested.load(Ordering::Relaxed) {
+                status.set(WorkerState::Idle);
+                tokio::select! {
+                    msg = content_recv.recv() => {
+                        status.set(WorkerState::Busy);
+                        match msg {
+                            Some(ContentMessage::Content(id, size)) if encountered_error.is_none() => {
+                                batch_contents.push((id, size));
+                            }
+                            Some(ContentMessage::ContentDone(files_sender, trees_sender)) => {
+                                batch_done_senders.push_back((files_sender, trees_sender));
+                            }
+                            Some(ContentMessage::Content(_, _)) => (),
+                            None => break,
+                        }
+                        if batch_contents.len() >= max_content_batch_size {
+                            if let Err(e) = ContentManager::flush_contents(&ctx, &repo_blobstore, &external_sender, compression, &progress, &mut batch_contents, &mut batch_done_senders, &mut encountered_error).await {
+                                tracing::error!("Contents flush failed: {:?}", e);
+                                status.set(WorkerState::Done);
+                                return;
+                            }
+                        }
+                    }
+                    _ = timer.tick() => {
+                        status.set(WorkerState::Busy);
+                        if let Err(e) = ContentManager::flush_contents(&ctx, &repo_blobstore, &external_sender, compression, &progress, &mut batch_contents, &mut batch_done_senders, &mut encountered_error).await {
+                            tracing::error!("Contents flush failed: {:?}", e);
+                            status.set(WorkerState::Done);
+                            return;
+                        }
+                    }
+                }
+            }
+            status.set(WorkerState::Done);
+        });
+    }
+}
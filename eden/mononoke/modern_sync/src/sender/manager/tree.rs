@@ -9,6 +9,7 @@ use std::collections::VecDeque;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use context::CoreContext;
@@ -23,18 +24,22 @@ use stats::define_stats;
 use stats::prelude::*;
 use tokio::sync::mpsc;
 use tokio::time::interval;
+use tranquilizer::Tranquilizer;
 
 use crate::sender::edenapi::EdenapiSender;
 use crate::sender::manager::Manager;
+use crate::sender::manager::SendManagerConfig;
+use crate::sender::manager::SyncProgress;
 use crate::sender::manager::TreeMessage;
-use crate::sender::manager::MAX_TREES_BATCH_SIZE;
+use crate::sender::manager::WorkerState;
+use crate::sender::manager::WorkerStatus;
 use crate::sender::manager::TREES_CHANNEL_SIZE;
-use crate::sender::manager::TREES_FLUSH_INTERVAL;
 
 define_stats! {
     prefix = "mononoke.modern_sync.manager.tree";
 
     synced_trees:  dynamic_timeseries("{}.synced_trees", (repo: String); Sum),
+    trees_dead_lettered:  dynamic_timeseries("{}.trees_dead_lettered", (repo: String); Sum),
     content_wait_time_s:  dynamic_timeseries("{}.content_wait_time_s", (repo: String); Average),
 
     trees_queue_capacity: dynamic_singleton_counter("{}.trees.queue_capacity", (repo: String)),
@@ -42,17 +47,100 @@ define_stats! {
     trees_queue_max_capacity: dynamic_singleton_counter("{}.trees.queue_max_capacity", (repo: String)),
 }
 
+// Tranquilizer window and delay factor: average upload latency over the last
+// 10 flushes drives the sleep, scaled up to 5x that average when the window
+// is backed up. This smooths out bursts in tree uploads without a fixed
+// rate-limit that would be wrong for both small and huge repos.
+const TRANQUILIZER_WINDOW: usize = 10;
+const TRANQUILIZER_DELAY_FACTOR: f64 = 5.0;
+
+// Exponential-backoff retry for `upload_trees`: after this many attempts a
+// batch is given up on and written to the dead-letter file instead of
+// blocking the rest of the sync indefinitely.
+const MAX_UPLOAD_TREES_ATTEMPTS: u32 = 5;
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Name of the file (relative to the process's working directory) that
+/// batches exhausting their upload retries are appended to, one JSON line
+/// per batch, so they can be inspected and replayed out of band instead of
+/// silently wedging the tree channel.
+const DEAD_LETTER_FILE: &str = "modern_sync_trees_dead_letter.jsonl";
+
+fn retry_backoff(attempt: u32) -> Duration {
+    let backoff = INITIAL_RETRY_BACKOFF.saturating_mul(1 << attempt.min(16));
+    backoff.min(MAX_RETRY_BACKOFF)
+}
+
+/// Whether `upload_trees` failing with `e` is worth retrying. `upload_trees`
+/// talks to a remote server over HTTP, so a dropped connection or a timeout
+/// is almost certainly transient, while an error that looks like a 4xx means
+/// the request itself is malformed or rejected and retrying it verbatim
+/// would just burn through `MAX_UPLOAD_TREES_ATTEMPTS` for no benefit.
+fn is_retryable_upload_error(e: &anyhow::Error) -> bool {
+    if let Some(io_err) = e.root_cause().downcast_ref::<std::io::Error>() {
+        use std::io::ErrorKind::*;
+        return matches!(
+            io_err.kind(),
+            ConnectionReset | ConnectionAborted | TimedOut | WouldBlock | Interrupted
+        );
+    }
+    let msg = e.to_string();
+    let looks_like_client_error = ["400", "401", "403", "404", "409", "422"]
+        .iter()
+        .any(|code| msg.contains(code));
+    !looks_like_client_error
+}
+
+/// Append a batch of manifest ids that exhausted their retries to the
+/// dead-letter file so it can be replayed later instead of being lost.
+fn write_dead_letter(reponame: &str, trees: &[HgManifestId], error: &anyhow::Error, logger: &Logger) {
+    use std::io::Write;
+
+    let line = serde_json::json!({
+        "reponame": reponame,
+        "trees": trees.iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+        "error": format!("{:?}", error),
+    });
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(DEAD_LETTER_FILE)
+        .and_then(|mut f| writeln!(f, "{}", line));
+    if let Err(e) = result {
+        error!(
+            logger,
+            "Failed to write {} trees to dead-letter file {}: {:?}",
+            trees.len(),
+            DEAD_LETTER_FILE,
+            e
+        );
+    }
+}
+
 pub(crate) struct TreeManager {
     trees_recv: mpsc::Receiver<TreeMessage>,
+    max_trees_batch_size: usize,
+    trees_flush_interval: Duration,
 }
 
 impl TreeManager {
-    pub(crate) fn new(trees_recv: mpsc::Receiver<TreeMessage>) -> Self {
-        Self { trees_recv }
+    pub(crate) fn new(trees_recv: mpsc::Receiver<TreeMessage>, config: &SendManagerConfig) -> Self {
+        Self {
+            trees_recv,
+            max_trees_batch_size: config.max_trees_batch_size,
+            trees_flush_interval: config.trees_flush_interval,
+        }
+    }
+
+    fn new_tranquilizer() -> Tranquilizer {
+        Tranquilizer::new(TRANQUILIZER_WINDOW, TRANQUILIZER_DELAY_FACTOR)
     }
 
     async fn flush_trees(
         trees_es: &Arc<EdenapiSender>,
+        progress: &SyncProgress,
+        tranquilizer: &mut Tranquilizer,
         batch_trees: &mut Vec<HgManifestId>,
         batch_done_senders: &mut VecDeque<oneshot::Sender<Result<()>>>,
         encountered_error: &mut Option<anyhow::Error>,
@@ -71,18 +159,86 @@ impl TreeManager {
             }
 
             if !batch_trees.is_empty() {
+                tranquilizer.sleep().await;
                 let start = std::time::Instant::now();
-                if let Err(e) = trees_es.upload_trees(std::mem::take(batch_trees)).await {
-                    error!(logger, "Failed to upload trees: {:?}", e);
-                    return Err(e);
-                } else {
+                // Tree sizes aren't known here, so the trees channel's byte
+                // counters hold the number of trees, not bytes.
+                progress.trees.record_enqueued(batch_size as u64);
+                let trees_to_upload = std::mem::take(batch_trees);
+
+                let mut attempt = 0;
+                let mut last_error = None;
+                let mut uploaded = false;
+                let mut remaining = trees_to_upload.clone();
+                while attempt < MAX_UPLOAD_TREES_ATTEMPTS {
+                    match trees_es.upload_trees(remaining.clone()).await {
+                        Ok(()) => {
+                            uploaded = true;
+                            break;
+                        }
+                        Err(e) => {
+                            attempt += 1;
+                            let retryable = is_retryable_upload_error(&e);
+                            if retryable && attempt < MAX_UPLOAD_TREES_ATTEMPTS {
+                                let backoff = retry_backoff(attempt);
+                                error!(
+                                    logger,
+                                    "Failed to upload {} trees (attempt {}/{}), retrying in {:?}: {:?}",
+                                    remaining.len(),
+                                    attempt,
+                                    MAX_UPLOAD_TREES_ATTEMPTS,
+                                    backoff,
+                                    e
+                                );
+                                tokio::time::sleep(backoff).await;
+                            }
+                            last_error = Some(e);
+                            if !retryable {
+                                break;
+                            }
+                        }
+                    }
+                }
+
+                if uploaded {
+                    progress.trees.record_sent(batch_size as u64, batch_size as u64);
+                    let elapsed = start.elapsed();
+                    tranquilizer.add_iteration_latency(elapsed);
                     info!(
                         logger,
                         "Uploaded {} trees in {}ms",
                         batch_size,
-                        start.elapsed().as_millis(),
+                        elapsed.as_millis(),
                     );
                     STATS::synced_trees.add_value(batch_size, (reponame.to_owned(),));
+                } else {
+                    let e = last_error.expect("loop always records an error when not uploaded");
+                    error!(
+                        logger,
+                        "Giving up after {} attempt(s) uploading {} trees, sending to dead-letter file: {:?}",
+                        attempt,
+                        remaining.len(),
+                        e
+                    );
+                    write_dead_letter(reponame, &remaining, &e, logger);
+                    STATS::trees_dead_lettered.add_value(remaining.len() as i64, (reponame.to_owned(),));
+
+                    // These trees were never synced, so anything gating
+                    // checkpoint/bookmark advancement on this batch must see
+                    // it as failed. We deliberately don't set
+                    // `encountered_error` here: that would wedge every later
+                    // batch too, defeating the point of dead-lettering this
+                    // one and moving on.
+                    let msg = format!(
+                        "{} trees failed to upload and were dead-lettered instead of synced: {:?}",
+                        remaining.len(),
+                        e
+                    );
+                    remaining.clear();
+                    while let Some(sender) = batch_done_senders.pop_front() {
+                        let _ = sender.send(Err(anyhow::anyhow!(msg.clone())));
+                    }
+                    return Ok(());
                 }
             }
 
@@ -107,18 +263,25 @@ impl Manager for TreeManager {
         trees_es: Arc<EdenapiSender>,
         logger: Logger,
         cancellation_requested: Arc<AtomicBool>,
+        progress: Arc<SyncProgress>,
+        status: Arc<WorkerStatus>,
     ) {
+        let max_trees_batch_size = self.max_trees_batch_size;
+        let trees_flush_interval = self.trees_flush_interval;
         mononoke::spawn_task(async move {
             let trees_recv = &mut self.trees_recv;
 
             let mut encountered_error: Option<anyhow::Error> = None;
             let mut batch_trees = Vec::new();
             let mut batch_done_senders = VecDeque::new();
-            let mut timer = interval(TREES_FLUSH_INTERVAL);
+            let mut timer = interval(trees_flush_interval);
+            let mut tranquilizer = TreeManager::new_tranquilizer();
 
             while !cancellation_requested.load(Ordering::Relaxed) {
+                status.set(WorkerState::Idle);
                 tokio::select! {
                     msg = trees_recv.recv() => {
+                        status.set(WorkerState::Busy);
                         debug!(logger, "Trees channel capacity: {} max capacity: {} in queue: {}", trees_recv.capacity(), TREES_CHANNEL_SIZE,  trees_recv.len());
                         STATS::trees_queue_capacity.set_value(ctx.fb, trees_recv.capacity() as i64, (reponame.clone(),));
                         STATS::trees_queue_len.add_value(trees_recv.len() as i64, (reponame.clone(),));
@@ -153,21 +316,25 @@ impl Manager for TreeManager {
                             Some(TreeMessage::Tree(_)) => (),
                             None => break,
                         }
-                        if batch_trees.len() >= MAX_TREES_BATCH_SIZE {
-                            if let Err(e) = TreeManager::flush_trees(&trees_es, &mut batch_trees, &mut batch_done_senders, &mut encountered_error, &reponame, &logger).await {
+                        if batch_trees.len() >= max_trees_batch_size {
+                            if let Err(e) = TreeManager::flush_trees(&trees_es, &progress, &mut tranquilizer, &mut batch_trees, &mut batch_done_senders, &mut encountered_error, &reponame, &logger).await {
+                                status.set(WorkerState::Done);
                                 error!(logger, "Trees flush failed: {:?}", e);
                                 return;
                             }
                         }
                     }
                     _ = timer.tick() => {
-                        if let Err(e) = TreeManager::flush_trees(&trees_es, &mut batch_trees, &mut batch_done_senders, &mut encountered_error, &reponame, &logger).await {
+                        status.set(WorkerState::Busy);
+                        if let Err(e) = TreeManager::flush_trees(&trees_es, &progress, &mut tranquilizer, &mut batch_trees, &mut batch_done_senders, &mut encountered_error, &reponame, &logger).await {
+                            status.set(WorkerState::Done);
                             error!(logger, "Trees flush failed: {:?}", e);
                             return;
                         }
                     }
                 }
             }
+            status.set(WorkerState::Done);
         });
     }
 }
@@ -0,0 +1,186 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This software may be used and distributed according to the terms of the
+ * GNU General Public License version 2.
+ */
+
+//! Graceful drain-and-restart supervision for the modern-sync tailer.
+//!
+//! The tail loop (`read_bookmark_update_log` feeding a `SendManager`) has no
+//! natural place to react to a SIGTERM/SIGHUP mid-sync: stopping abruptly
+//! can drop in-flight `ChangesetMessage`s and leave the persisted counter
+//! pointing past work that never actually completed. `ShutdownSignal` gives
+//! the outer loop a flag to poll between bookmark-update-log batches (it's
+//! the same flag `SendManager::new` takes as `cancellation_requested`, so
+//! signalling the process stops both the batch producer and the per-channel
+//! workers), and `run_with_restart` wraps the whole thing so transient send
+//! errors restart the tailer -- resuming from the last persisted
+//! checkpoint/counter -- instead of aborting the run.
+//!
+//! Wiring it in is three calls from whatever drives the tail loop: call
+//! `ShutdownSignal::install` once at startup and hand `.flag()` to
+//! `SendManager::new` as `cancellation_requested`; wrap each attempt at the
+//! loop in `run_with_restart(logger, reponame, shutdown.clone(), policy,
+//! || async { .. })`; and call `drain_in_flight_changesets(&send_manager)`
+//! once `shutdown.is_requested()` is observed between batches, before
+//! returning `Ok(())` out of that attempt. In this checkout that driver is
+//! `crate::sync::sync` (the loop over `ExecutionType::Tail` called from
+//! `commands/sync_loop.rs`), neither of which is present here, so none of
+//! the three calls above have a call site yet -- this module isn't reachable
+//! from any `mod` declaration in this tree for the same reason. The pieces
+//! below are otherwise ready to use as soon as that loop exists.
+
+use std::future::Future;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::channel::oneshot;
+use mononoke_macros::mononoke;
+use rand::Rng;
+use slog::error;
+use slog::info;
+use slog::warn;
+use slog::Logger;
+
+use crate::sender::manager::ChangesetMessage;
+use crate::sender::manager::SendManager;
+
+/// Shared flag set by `ShutdownSignal::install` and polled by the tail loop
+/// between `BookmarkUpdateLogEntry` batches.
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    requested: Arc<AtomicBool>,
+}
+
+impl ShutdownSignal {
+    /// Installs handlers for SIGTERM and SIGHUP that set the shared flag.
+    /// Both are treated as a request for a graceful drain-and-exit rather
+    /// than distinguishing "terminate" from "reload", since this tailer has
+    /// no reloadable config to act on a HUP alone.
+    pub fn install(logger: Logger) -> Self {
+        let requested = Arc::new(AtomicBool::new(false));
+        for (kind, name) in [
+            (tokio::signal::unix::SignalKind::terminate(), "SIGTERM"),
+            (tokio::signal::unix::SignalKind::hangup(), "SIGHUP"),
+        ] {
+            let requested = requested.clone();
+            let logger = logger.clone();
+            match tokio::signal::unix::signal(kind) {
+                Ok(mut stream) => {
+                    mononoke::spawn_task(async move {
+                        stream.recv().await;
+                        info!(logger, "Received {}, requesting graceful shutdown", name);
+                        requested.store(true, Ordering::Relaxed);
+                    });
+                }
+                Err(e) => {
+                    warn!(logger, "Failed to install {} handler: {:?}", name, e);
+                }
+            }
+        }
+        Self { requested }
+    }
+
+    /// The flag itself, for handing to `SendManager::new`'s
+    /// `cancellation_requested` parameter and to the bookmark-update-log
+    /// polling loop.
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.requested.clone()
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+}
+
+/// Blocks until every already-queued changeset has either been sent or
+/// failed, via the same `ChangesetMessage::NotifyCompletion` barrier
+/// `sync_one` uses to wait out a single changeset. Call this once a
+/// shutdown has been requested and no further `BookmarkUpdateLogEntry`
+/// batches are being pulled, so the mutable counter only ever advances past
+/// entries that fully drained.
+pub async fn drain_in_flight_changesets(send_manager: &SendManager) -> Result<()> {
+    let (finish_tx, finish_rx) = oneshot::channel();
+    send_manager
+        .send_changeset(ChangesetMessage::NotifyCompletion(finish_tx))
+        .await?;
+    finish_rx.await??;
+    Ok(())
+}
+
+/// Backoff schedule for `run_with_restart`: exponential and capped, with
+/// jitter that picks a random 10-100% of the capped delay, tuned for a
+/// long-running tailer rather than a single RPC.
+#[derive(Clone, Copy, Debug)]
+pub struct RestartPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        let capped = self
+            .base_delay
+            .saturating_mul(1u32.checked_shl(attempt.min(16)).unwrap_or(u32::MAX))
+            .min(self.max_delay);
+        capped.mul_f64(rand::thread_rng().gen::<f64>().max(0.1))
+    }
+}
+
+/// Drives `run_once` (a single attempt at the tail loop) until it returns
+/// `Ok(())` (a clean, shutdown-requested exit) or `shutdown` is set: on an
+/// `Err` while not shutting down, logs it, backs off per `policy`, and
+/// calls `run_once` again. Each attempt is expected to build its own
+/// `SendManager` (so it picks up the persisted checkpoint/mutable-counter
+/// position) rather than reusing a sender across restarts, so the tailer
+/// behaves like a restart-on-failure service rather than aborting the whole
+/// run on the first transient error.
+pub async fn run_with_restart<F, Fut>(
+    logger: Logger,
+    reponame: String,
+    shutdown: ShutdownSignal,
+    policy: RestartPolicy,
+    mut run_once: F,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut attempt = 0u32;
+    loop {
+        match run_once().await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                if shutdown.is_requested() {
+                    info!(
+                        logger,
+                        "Tailer for {} stopped (shutdown requested) after error: {:?}",
+                        reponame,
+                        e
+                    );
+                    return Ok(());
+                }
+                error!(
+                    logger,
+                    "Tailer for {} failed, restarting: {:?}", reponame, e
+                );
+                let delay = policy.delay(attempt);
+                attempt = attempt.saturating_add(1);
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
@@ -5,95 +5,429 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
 
 use configmodel::Config;
+use rand::Rng;
 use futures::stream::BoxStream;
 pub use types::CasDigest;
 pub use types::CasDigestType;
 pub use types::CasFetchedStats;
 pub use types::FetchContext;
 
+/// Selects how [`CasSuccessTracker`] decides the CAS backend is unhealthy.
+#[derive(Clone, Copy, Debug)]
+pub enum CasTrippingPolicy {
+    /// Trip after `max_failures` consecutive failures, recovering via a
+    /// decorrelated-jitter backoff with a single half-open probe per window.
+    /// This is the default: cheap, and a good fit for steady traffic.
+    ConsecutiveFailures,
+    /// Trip based on the failure ratio over a rolling time window instead of
+    /// a raw consecutive-failure count. Avoids tripping on a handful of
+    /// failures during low traffic while still reacting quickly to
+    /// sustained degradation under load.
+    SlidingWindow {
+        /// How far back to look when computing the failure ratio.
+        window: Duration,
+        /// Minimum number of requests that must have been recorded in
+        /// `window` before the ratio is trusted enough to trip the breaker.
+        min_request_volume: usize,
+        /// Failure percentage (0-100) above which the breaker trips.
+        error_threshold_pct: f64,
+    },
+}
+
+impl Default for CasTrippingPolicy {
+    fn default() -> Self {
+        CasTrippingPolicy::ConsecutiveFailures
+    }
+}
+
 pub struct CasSuccessTrackerConfig {
     // number of failures before the CAS is considered unhealthy
     pub max_failures: usize,
     // how long to wait before allowing requests again after a failure
     // this is used as initial downtime, and then it is exponentially increased if the request fails again
     pub downtime_on_failure: Duration,
+    // which tripping policy `allow_request` should use
+    pub tripping_policy: CasTrippingPolicy,
+}
+
+/// Upper bound on how many recent outcomes [`CasSuccessTracker`] retains for
+/// [`CasTrippingPolicy::SlidingWindow`], so a very long `window` can't grow
+/// the ring buffer without bound under sustained traffic.
+const SLIDING_WINDOW_RING_CAPACITY: usize = 4096;
+
+fn now_ms() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64)
+}
+
+/// Returns a jittered backoff window, in ms, uniformly distributed over
+/// `[low, high]`. Overridable per-instance (see
+/// [`CasSuccessTracker::new_with_jitter`]) so tests can be deterministic.
+fn default_jitter_range_ms(low: u64, high: u64) -> u64 {
+    if high <= low {
+        return low;
+    }
+    rand::thread_rng().gen_range(low..=high)
+}
+
+/// Coarse breaker state for introspection/debug surfaces -- see
+/// [`CasSuccessTracker::health_snapshot`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Requests are allowed through normally.
+    Healthy,
+    /// The backoff window (or, under `SlidingWindow`, the error rate) is
+    /// still past its threshold; all requests are denied.
+    Open,
+    /// The backoff window has elapsed and a single probe request is (or is
+    /// about to be) admitted to test recovery. Only reachable under
+    /// `CasTrippingPolicy::ConsecutiveFailures`.
+    HalfOpen,
+    /// An operator called [`CasSuccessTracker::force_trip`]; denies
+    /// everything until [`CasSuccessTracker::force_reset`].
+    ManuallyTripped,
+}
+
+/// Point-in-time snapshot of a [`CasSuccessTracker`]'s health, for a debug
+/// command or management endpoint to render (e.g. as JSON) without poking at
+/// the tracker's internals directly. Unlike `allow_request`, producing this
+/// snapshot never mutates tracker state (e.g. it never admits a half-open
+/// probe).
+#[derive(Clone, Copy, Debug)]
+pub struct CasHealthSnapshot {
+    pub breaker_state: BreakerState,
+    pub failures_since_last_success: usize,
+    pub current_backoff_window: Duration,
+    pub number_of_downtimes: usize,
 }
 
 pub struct CasSuccessTracker {
-    pub config: CasSuccessTrackerConfig,
+    // swappable behind a mutex so `reconfigure` can retune a running
+    // tracker (`max_failures`, `downtime_on_failure`, `tripping_policy`)
+    // without rebuilding it.
+    config: Mutex<Arc<CasSuccessTrackerConfig>>,
     // number of failures since last success
     pub failures_since_last_success: AtomicUsize,
     // timestamp of the last failure
     // number of ms since the Unix epoch
     pub last_failure_ms: AtomicU64,
-    pub downtime_on_failure_ms: u64,
     // number of times the downtime has been lifted on sequential failures
-    // used to calculate exponential backoff
     // the counter is reset on success
     pub number_of_downtimes: AtomicUsize,
+    // the decorrelated-jitter backoff window computed by the most recent
+    // `record_failure`, in ms: a random value in
+    // `[downtime_on_failure_ms, min(cap, prev_window * 3)]`. `allow_request`
+    // compares elapsed time against this rather than a fixed multiple of
+    // `downtime_on_failure_ms`, so recovering CAS backends aren't hit by
+    // every Sapling client retrying in lockstep.
+    current_window_ms: AtomicU64,
+    // the window computed by the failure before that, used as the basis for
+    // decorrelating the next one.
+    prev_window_ms: AtomicU64,
+    // set while a single half-open probe request is outstanding once the
+    // backoff window has expired; cleared by `record_success` (closing the
+    // breaker) or `record_failure` (re-opening it). Ensures only one caller
+    // is admitted per window instead of a thundering herd.
+    probe_in_flight: AtomicBool,
+    // source of randomness for `current_window_ms`; overridden in tests for
+    // determinism.
+    jitter_range_ms: fn(u64, u64) -> u64,
+    // ring buffer of (timestamp_ms, success) for `CasTrippingPolicy::SlidingWindow`.
+    // Kept populated regardless of the active policy (a couple of pushes per
+    // request is cheap) so switching policies doesn't require a warm-up.
+    recent_outcomes: Mutex<VecDeque<(u64, bool)>>,
+    // set by `force_trip`, cleared by `force_reset`; checked before any
+    // policy-specific logic in `allow_request`, so an operator can drain a
+    // backend they know is bad regardless of its recent success/failure
+    // history.
+    manually_tripped: AtomicBool,
 }
 
 impl CasSuccessTracker {
     pub fn new(config: CasSuccessTrackerConfig) -> Self {
+        Self::new_with_jitter(config, default_jitter_range_ms)
+    }
+
+    fn new_with_jitter(config: CasSuccessTrackerConfig, jitter_range_ms: fn(u64, u64) -> u64) -> Self {
         let downtime_on_failure_ms = config.downtime_on_failure.as_millis() as u64;
         Self {
-            config,
+            config: Mutex::new(Arc::new(config)),
             failures_since_last_success: AtomicUsize::new(0),
             last_failure_ms: AtomicU64::new(0),
-            downtime_on_failure_ms,
             number_of_downtimes: AtomicUsize::new(0),
+            current_window_ms: AtomicU64::new(downtime_on_failure_ms),
+            prev_window_ms: AtomicU64::new(downtime_on_failure_ms),
+            probe_in_flight: AtomicBool::new(false),
+            jitter_range_ms,
+            recent_outcomes: Mutex::new(VecDeque::new()),
+            manually_tripped: AtomicBool::new(false),
+        }
+    }
+
+    fn current_config(&self) -> Arc<CasSuccessTrackerConfig> {
+        self.config
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Swaps in a new tunable config (`max_failures`, `downtime_on_failure`,
+    /// `tripping_policy`) without rebuilding the tracker, so an operator can
+    /// retune breaker behavior on a running process.
+    pub fn reconfigure(&self, new_config: CasSuccessTrackerConfig) {
+        let mut guard = self
+            .config
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *guard = Arc::new(new_config);
+    }
+
+    /// Forces the breaker open regardless of recent successes/failures, e.g.
+    /// to drain traffic away from a backend an operator already knows is
+    /// bad. Stays open until [`Self::force_reset`] is called.
+    pub fn force_trip(&self) {
+        self.manually_tripped.store(true, Ordering::Relaxed);
+    }
+
+    /// Clears a manual trip (if any) and resets failure/backoff state,
+    /// exactly as if the most recent request had succeeded.
+    pub fn force_reset(&self) {
+        self.manually_tripped.store(false, Ordering::Relaxed);
+        self.record_success();
+    }
+
+    /// Builds a [`CasHealthSnapshot`] without mutating any tracker state,
+    /// unlike `allow_request`, which can admit a half-open probe as a side
+    /// effect.
+    pub fn health_snapshot(&self) -> anyhow::Result<CasHealthSnapshot> {
+        let failures = self.failures_since_last_success.load(Ordering::Relaxed);
+        let number_of_downtimes = self.number_of_downtimes.load(Ordering::Relaxed);
+        let current_backoff_window =
+            Duration::from_millis(self.current_window_ms.load(Ordering::Relaxed));
+
+        let breaker_state = if self.manually_tripped.load(Ordering::Relaxed) {
+            BreakerState::ManuallyTripped
+        } else {
+            match self.current_config().tripping_policy {
+                CasTrippingPolicy::ConsecutiveFailures => {
+                    if failures < self.current_config().max_failures {
+                        BreakerState::Healthy
+                    } else {
+                        let last_failure = self.last_failure_ms.load(Ordering::Relaxed);
+                        let window = self.current_window_ms.load(Ordering::Relaxed);
+                        if now_ms()?.saturating_sub(last_failure) < window {
+                            BreakerState::Open
+                        } else {
+                            BreakerState::HalfOpen
+                        }
+                    }
+                }
+                CasTrippingPolicy::SlidingWindow {
+                    window,
+                    min_request_volume,
+                    error_threshold_pct,
+                } => {
+                    if self.sliding_window_error_rate_exceeded(
+                        window,
+                        min_request_volume,
+                        error_threshold_pct,
+                    )? {
+                        BreakerState::Open
+                    } else {
+                        BreakerState::Healthy
+                    }
+                }
+            }
+        };
+
+        Ok(CasHealthSnapshot {
+            breaker_state,
+            failures_since_last_success: failures,
+            current_backoff_window,
+            number_of_downtimes,
+        })
+    }
+
+    /// Read-only counterpart of [`Self::allow_request_sliding_window`]'s
+    /// ratio check: doesn't evict stale entries, so it's safe to call from
+    /// [`Self::health_snapshot`] without mutating the ring buffer.
+    fn sliding_window_error_rate_exceeded(
+        &self,
+        window: Duration,
+        min_request_volume: usize,
+        error_threshold_pct: f64,
+    ) -> anyhow::Result<bool> {
+        let now = now_ms()?;
+        let window_ms = window.as_millis() as u64;
+
+        let outcomes = self
+            .recent_outcomes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut total = 0usize;
+        let mut failures = 0usize;
+        for (ts, success) in outcomes.iter() {
+            if now.saturating_sub(*ts) <= window_ms {
+                total += 1;
+                if !success {
+                    failures += 1;
+                }
+            }
+        }
+
+        if total < min_request_volume {
+            return Ok(false);
+        }
+        let error_pct = (failures as f64 / total as f64) * 100.0;
+        Ok(error_pct > error_threshold_pct)
+    }
+
+    fn record_outcome(&self, success: bool) {
+        if let Ok(now) = now_ms() {
+            let mut outcomes = self
+                .recent_outcomes
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            outcomes.push_back((now, success));
+            while outcomes.len() > SLIDING_WINDOW_RING_CAPACITY {
+                outcomes.pop_front();
+            }
         }
     }
 
     pub fn record_success(&self) {
+        self.record_outcome(true);
+        let downtime_on_failure_ms = self.current_config().downtime_on_failure.as_millis() as u64;
         self.failures_since_last_success.store(0, Ordering::Relaxed);
         self.number_of_downtimes.store(0, Ordering::Relaxed);
+        self.prev_window_ms
+            .store(downtime_on_failure_ms, Ordering::Relaxed);
+        self.current_window_ms
+            .store(downtime_on_failure_ms, Ordering::Relaxed);
+        self.probe_in_flight.store(false, Ordering::Relaxed);
     }
 
     pub fn record_failure(&self) -> anyhow::Result<()> {
+        self.record_outcome(false);
         self.failures_since_last_success
             .fetch_add(1, Ordering::Relaxed);
-        Ok(self.last_failure_ms.store(
-            SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
-            Ordering::Relaxed,
-        ))
+        self.probe_in_flight.store(false, Ordering::Relaxed);
+
+        // decorrelated jitter (as used by e.g. AWS's retry guidance): the
+        // next window is random within [base, min(cap, prev_window * 3)],
+        // capped at the same 16x we used to apply as a fixed multiplier.
+        let downtime_on_failure_ms = self.current_config().downtime_on_failure.as_millis() as u64;
+        let cap = downtime_on_failure_ms.saturating_mul(16);
+        let prev_window = self
+            .prev_window_ms
+            .load(Ordering::Relaxed)
+            .max(downtime_on_failure_ms);
+        let high = std::cmp::min(cap, prev_window.saturating_mul(3)).max(downtime_on_failure_ms);
+        let window = (self.jitter_range_ms)(downtime_on_failure_ms, high);
+        self.current_window_ms.store(window, Ordering::Relaxed);
+        self.prev_window_ms.store(window, Ordering::Relaxed);
+
+        self.last_failure_ms.store(now_ms()?, Ordering::Relaxed);
+        Ok(())
     }
 
     pub fn allow_request(&self) -> anyhow::Result<bool> {
+        if self.manually_tripped.load(Ordering::Relaxed) {
+            tracing::warn!(target: "cas", "CAS breaker manually tripped, should not be used at this time");
+            return Ok(false);
+        }
+
+        match self.current_config().tripping_policy {
+            CasTrippingPolicy::ConsecutiveFailures => self.allow_request_consecutive_failures(),
+            CasTrippingPolicy::SlidingWindow {
+                window,
+                min_request_volume,
+                error_threshold_pct,
+            } => self.allow_request_sliding_window(window, min_request_volume, error_threshold_pct),
+        }
+    }
+
+    fn allow_request_consecutive_failures(&self) -> anyhow::Result<bool> {
         let failures = self.failures_since_last_success.load(Ordering::Relaxed);
-        if failures >= self.config.max_failures {
-            let last_failure = self.last_failure_ms.load(Ordering::Relaxed);
-            let time_now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
-            let number_of_downtimes = self.number_of_downtimes.load(Ordering::Relaxed);
-            // exponential backoff coefficient
-            let expn_backoff_coefficient = std::cmp::min(1 << number_of_downtimes, 16);
-            // the request is allowed if the downtime has expired with exponential backoff (capped)
-            // the downtime would be:
-            // 1 * downtime_on_failure_ms, 2 * downtime_on_failure_ms, 4 * downtime_on_failure_ms
-            // 8 * downtime_on_failure_ms, 16 * downtime_on_failure_ms (this will be the max)
-            //
-            // if it has been too long since the last request was allowed, allow the request now!
-            if time_now - last_failure >= self.downtime_on_failure_ms * expn_backoff_coefficient {
-                self.number_of_downtimes.fetch_add(1, Ordering::Relaxed);
-                // reset the counter, because we would like to allow at least max_failures before
-                // we start to apply the downtime again
-                self.failures_since_last_success.store(0, Ordering::Relaxed);
-                return Ok(true);
-            }
-            // otherwise, don't allow the request
+        if failures < self.current_config().max_failures {
+            // CAS is considered healthy if it has not failed too many times
+            return Ok(true);
+        }
+
+        let last_failure = self.last_failure_ms.load(Ordering::Relaxed);
+        let time_now = now_ms()?;
+        let window = self.current_window_ms.load(Ordering::Relaxed);
+
+        if time_now.saturating_sub(last_failure) < window {
+            // the backoff window hasn't elapsed yet
             tracing::warn!(target: "cas", "CAS is unhealthy, should not be used at this time");
             return Ok(false);
         }
-        // CAS is considered healthy if it has not failed too many times
+
+        // the backoff window has elapsed: admit exactly one half-open probe
+        // request, rather than letting every caller through at once.
+        let admitted = self
+            .probe_in_flight
+            .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok();
+        if admitted {
+            self.number_of_downtimes.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(admitted)
+    }
+
+    /// Trips when both (a) at least `min_request_volume` outcomes have been
+    /// recorded within `window`, and (b) the failure ratio among them
+    /// exceeds `error_threshold_pct`. Unlike
+    /// `allow_request_consecutive_failures`, recovery isn't gated behind a
+    /// half-open probe: the breaker simply reopens as soon as the ratio
+    /// drops back under the threshold (which happens naturally as stale
+    /// entries age out of the window).
+    fn allow_request_sliding_window(
+        &self,
+        window: Duration,
+        min_request_volume: usize,
+        error_threshold_pct: f64,
+    ) -> anyhow::Result<bool> {
+        let now = now_ms()?;
+        let window_ms = window.as_millis() as u64;
+
+        let mut outcomes = self
+            .recent_outcomes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while matches!(outcomes.front(), Some((ts, _)) if now.saturating_sub(*ts) > window_ms) {
+            outcomes.pop_front();
+        }
+
+        let total = outcomes.len();
+        if total < min_request_volume {
+            // Not enough data yet to trust the ratio.
+            return Ok(true);
+        }
+
+        let failures = outcomes.iter().filter(|(_, success)| !success).count();
+        let error_pct = (failures as f64 / total as f64) * 100.0;
+        if error_pct > error_threshold_pct {
+            tracing::warn!(
+                target: "cas",
+                "CAS error rate {:.1}% over last {} requests exceeds threshold {:.1}%, should not be used at this time",
+                error_pct, total, error_threshold_pct,
+            );
+            return Ok(false);
+        }
+
         Ok(true)
     }
 }
@@ -157,6 +491,19 @@ impl CasClientFetchedBytes {
     }
 }
 
+/// Aggregate introspection snapshot returned by [`CasClient::health`], meant
+/// to be rendered as-is by a debug command (e.g. as JSON) -- the in-process
+/// equivalent of a daemon-info endpoint, without requiring an HTTP server in
+/// this crate.
+#[derive(Clone, Debug, Default)]
+pub struct CasClientHealth {
+    /// `None` for a `CasClient` implementation that doesn't embed a
+    /// [`CasSuccessTracker`] (e.g. a mock used in tests).
+    pub tracker: Option<CasHealthSnapshot>,
+    /// Fetch/prefetch stats accumulated since the client was constructed.
+    pub accumulated_stats: CasFetchedStats,
+}
+
 #[async_trait::async_trait]
 #[auto_impl::auto_impl(&, Box, Arc)]
 pub trait CasClient: Sync + Send {
@@ -182,18 +529,52 @@ pub trait CasClient: Sync + Send {
         digests: &'a [CasDigest],
         log_name: CasDigestType,
     ) -> BoxStream<'a, anyhow::Result<(CasFetchedStats, Vec<CasDigest>, Vec<CasDigest>)>>;
+
+    /// Point-in-time operational snapshot: breaker state, failure counters,
+    /// current backoff window, and accumulated fetch/prefetch stats -- so a
+    /// debug command can render CAS health without restarting the process.
+    /// Default implementation reports nothing, since not every `CasClient`
+    /// embeds a [`CasSuccessTracker`].
+    fn health(&self) -> CasClientHealth {
+        CasClientHealth::default()
+    }
+
+    /// Live-reconfigures this client's success tracker, if it has one
+    /// (`max_failures`, `downtime_on_failure`, `tripping_policy`). No-op by
+    /// default.
+    fn reconfigure(&self, _config: CasSuccessTrackerConfig) {}
+
+    /// Forces this client's breaker open (if it has one), e.g. to drain
+    /// traffic away from a backend an operator already knows is bad. No-op
+    /// by default.
+    fn force_trip(&self) {}
+
+    /// Clears a manual trip and resets failure/backoff state, as if this
+    /// client's last request had succeeded. No-op by default.
+    fn force_reset(&self) {}
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+
+    // Deterministic stand-ins for `default_jitter_range_ms`, so timing
+    // assertions below don't depend on real randomness.
+    fn min_jitter(low: u64, _high: u64) -> u64 {
+        low
+    }
+    fn max_jitter(_low: u64, high: u64) -> u64 {
+        high
+    }
+
     #[test]
     fn test_success_tracker() {
         let config = CasSuccessTrackerConfig {
             max_failures: 3,
             downtime_on_failure: Duration::from_secs(1),
+            tripping_policy: CasTrippingPolicy::default(),
         };
-        let tracker = CasSuccessTracker::new(config);
+        let tracker = CasSuccessTracker::new_with_jitter(config, min_jitter);
 
         // Test that the tracker allows requests when it's healthy
         assert!(tracker.allow_request().unwrap());
@@ -204,50 +585,174 @@ mod tests {
         }
         assert!(!tracker.allow_request().unwrap());
 
-        // Test that the tracker allows requests after the downtime has passed
+        // Test that the tracker admits a single half-open probe after the
+        // downtime has passed
         std::thread::sleep(Duration::from_secs(1));
         assert!(tracker.allow_request().unwrap());
+        // ...and nobody else until that probe resolves
+        assert!(!tracker.allow_request().unwrap());
+
+        // A successful probe closes the breaker
+        tracker.record_success();
+        assert!(tracker.allow_request().unwrap());
 
         for _ in 0..3 {
             tracker.record_failure().unwrap();
         }
         assert!(!tracker.allow_request().unwrap());
 
-        // Test that the tracker does not allow requests after the downtime has passed again (from the last failure)
-        std::thread::sleep(Duration::from_secs(1));
+        // Test that the tracker allows requests after there was a success after a failure
+        tracker.record_success();
+        assert!(tracker.allow_request().unwrap());
+    }
+
+    #[test]
+    fn test_success_tracker_half_open_single_probe() {
+        let config = CasSuccessTrackerConfig {
+            max_failures: 1,
+            downtime_on_failure: Duration::from_secs(1),
+            tripping_policy: CasTrippingPolicy::default(),
+        };
+        let tracker = CasSuccessTracker::new_with_jitter(config, min_jitter);
+        tracker.record_failure().unwrap();
         assert!(!tracker.allow_request().unwrap());
 
-        // Test that the tracker does allow requests after 2 times the downtime has passed (1+1 seconds)
         std::thread::sleep(Duration::from_secs(1));
+        // Exactly one caller is admitted once the window elapses...
+        assert!(tracker.allow_request().unwrap());
+        // ...and everyone else still sees the breaker open.
+        assert!(!tracker.allow_request().unwrap());
+        assert!(!tracker.allow_request().unwrap());
+
+        // A failed probe re-opens the breaker for the next window.
+        tracker.record_failure().unwrap();
+        assert!(!tracker.allow_request().unwrap());
+    }
+
+    #[test]
+    fn test_success_tracker_decorrelated_jitter_backoff() {
+        let config = CasSuccessTrackerConfig {
+            max_failures: 1,
+            downtime_on_failure: Duration::from_millis(100),
+            tripping_policy: CasTrippingPolicy::default(),
+        };
+        let tracker = CasSuccessTracker::new_with_jitter(config, max_jitter);
+        tracker.record_failure().unwrap();
+        // With jitter pinned to the top of its range, each window is
+        // min(cap, prev_window * 3): 300, 900, then capped at 16x base (1600).
+        for window_ms in [300, 900, 1600, 1600] {
+            std::thread::sleep(Duration::from_millis(window_ms - 50));
+            assert!(!tracker.allow_request().unwrap()); // window not yet elapsed
+            std::thread::sleep(Duration::from_millis(100));
+            assert!(tracker.allow_request().unwrap()); // window elapsed, probe admitted
+            tracker.record_failure().unwrap(); // probe failed, re-open for the next window
+        }
+    }
+
+    #[test]
+    fn test_success_tracker_sliding_window() {
+        let config = CasSuccessTrackerConfig {
+            max_failures: usize::MAX,
+            downtime_on_failure: Duration::from_secs(1),
+            tripping_policy: CasTrippingPolicy::SlidingWindow {
+                window: Duration::from_millis(200),
+                min_request_volume: 4,
+                error_threshold_pct: 50.0,
+            },
+        };
+        let tracker = CasSuccessTracker::new_with_jitter(config, min_jitter);
+
+        // Below the volume threshold: allowed even though every request failed.
+        tracker.record_failure().unwrap();
+        tracker.record_failure().unwrap();
+        tracker.record_failure().unwrap();
         assert!(tracker.allow_request().unwrap());
 
+        // Volume threshold reached with a 75% failure ratio: breaker trips.
+        tracker.record_failure().unwrap();
+        assert!(!tracker.allow_request().unwrap());
+
+        // Enough successes bring the ratio back under the threshold
+        // (4 failures out of 8 is 50%, which does not exceed it).
+        tracker.record_success();
+        tracker.record_success();
+        tracker.record_success();
         tracker.record_success();
         assert!(tracker.allow_request().unwrap());
 
-        for _ in 0..3 {
+        // Once the window ages out, stale failures no longer count.
+        for _ in 0..4 {
             tracker.record_failure().unwrap();
         }
         assert!(!tracker.allow_request().unwrap());
-
-        // Test that the tracker allows requests after there was a success after a failure
-        tracker.record_success();
+        std::thread::sleep(Duration::from_millis(250));
         assert!(tracker.allow_request().unwrap());
     }
 
     #[test]
-    fn test_success_tracker_exponential_backoff() {
+    fn test_success_tracker_health_snapshot_and_manual_override() {
         let config = CasSuccessTrackerConfig {
             max_failures: 1,
             downtime_on_failure: Duration::from_secs(1),
+            tripping_policy: CasTrippingPolicy::default(),
         };
-        let tracker = CasSuccessTracker::new(config);
+        let tracker = CasSuccessTracker::new_with_jitter(config, min_jitter);
+
+        assert_eq!(
+            tracker.health_snapshot().unwrap().breaker_state,
+            BreakerState::Healthy
+        );
+
         tracker.record_failure().unwrap();
-        for i in [1, 2, 4, 8] {
-            std::thread::sleep(Duration::from_secs(i - 1));
-            assert!(!tracker.allow_request().unwrap()); // exponential backoff is not yet lifted
-            std::thread::sleep(Duration::from_secs(1));
-            assert!(tracker.allow_request().unwrap()); // exponential backoff is lifted
-            tracker.record_failure().unwrap();
-        }
+        assert_eq!(
+            tracker.health_snapshot().unwrap().breaker_state,
+            BreakerState::Open
+        );
+        // Unlike `allow_request`, reading the snapshot repeatedly doesn't
+        // admit a half-open probe.
+        assert_eq!(
+            tracker.health_snapshot().unwrap().breaker_state,
+            BreakerState::Open
+        );
+
+        std::thread::sleep(Duration::from_secs(1));
+        assert_eq!(
+            tracker.health_snapshot().unwrap().breaker_state,
+            BreakerState::HalfOpen
+        );
+        // health_snapshot() doesn't consume the probe slot.
+        assert!(tracker.allow_request().unwrap());
+
+        tracker.record_success();
+        assert_eq!(
+            tracker.health_snapshot().unwrap().breaker_state,
+            BreakerState::Healthy
+        );
+
+        // A manual trip overrides the otherwise-healthy breaker...
+        tracker.force_trip();
+        assert_eq!(
+            tracker.health_snapshot().unwrap().breaker_state,
+            BreakerState::ManuallyTripped
+        );
+        assert!(!tracker.allow_request().unwrap());
+
+        // ...until force_reset clears it.
+        tracker.force_reset();
+        assert_eq!(
+            tracker.health_snapshot().unwrap().breaker_state,
+            BreakerState::Healthy
+        );
+        assert!(tracker.allow_request().unwrap());
+
+        // reconfigure() takes effect immediately for subsequent calls.
+        tracker.reconfigure(CasSuccessTrackerConfig {
+            max_failures: 1,
+            downtime_on_failure: Duration::from_secs(1),
+            tripping_policy: CasTrippingPolicy::default(),
+        });
+        assert!(tracker.allow_request().unwrap());
+        tracker.record_failure().unwrap();
+        assert!(!tracker.allow_request().unwrap());
     }
 }
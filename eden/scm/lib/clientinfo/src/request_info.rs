@@ -6,9 +6,16 @@
  */
 
 use std::fmt::Display;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
 
 use anyhow::anyhow;
 use anyhow::Result;
+use crossbeam::channel::bounded;
+use crossbeam::channel::Receiver;
+use crossbeam::channel::Sender;
+use crossbeam::channel::TrySendError;
 use rand::distributions::Alphanumeric;
 use rand::thread_rng;
 use rand::Rng;
@@ -69,6 +76,93 @@ impl ClientRequestInfo {
             .map(char::from)
             .collect()
     }
+
+    /// Push a structured tracing event onto the bounded, lock-free event
+    /// channel, stamped with this request's `correlator` and `entry_point`.
+    /// Never blocks: if the channel is full the event is dropped and counted
+    /// in `dropped_event_count`, rather than stalling the caller.
+    pub fn event(&self, level: EventLevel, fields: Vec<(&'static str, String)>) {
+        let event = TracingEvent {
+            correlator: self.correlator.clone(),
+            entry_point: self.entry_point.clone(),
+            level,
+            fields,
+        };
+        match event_sender().try_send(event) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                dropped_event_count().fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Severity of a `ClientRequestInfo::event` entry.
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub enum EventLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// A single correlator-scoped tracing event, ready to be forwarded to the
+/// tracing backend by the collector.
+#[derive(Clone, Debug)]
+pub struct TracingEvent {
+    pub correlator: String,
+    pub entry_point: ClientEntryPoint,
+    pub level: EventLevel,
+    pub fields: Vec<(&'static str, String)>,
+}
+
+/// Capacity of the bounded event channel. Sized generously so a burst of
+/// concurrent workers doesn't drop events under normal load; once full,
+/// `ClientRequestInfo::event` drops rather than blocks.
+const EVENT_CHANNEL_CAPACITY: usize = 16_384;
+
+static EVENT_CHANNEL: OnceLock<(Sender<TracingEvent>, Receiver<TracingEvent>)> = OnceLock::new();
+static DROPPED_EVENT_COUNT: OnceLock<AtomicU64> = OnceLock::new();
+
+fn event_channel() -> &'static (Sender<TracingEvent>, Receiver<TracingEvent>) {
+    EVENT_CHANNEL.get_or_init(|| bounded(EVENT_CHANNEL_CAPACITY))
+}
+
+fn event_sender() -> &'static Sender<TracingEvent> {
+    &event_channel().0
+}
+
+fn dropped_event_count() -> &'static AtomicU64 {
+    DROPPED_EVENT_COUNT.get_or_init(|| AtomicU64::new(0))
+}
+
+/// Number of events dropped so far because the channel was full. Exposed so
+/// the collector can report backpressure instead of silently losing spans.
+pub fn dropped_event_count_total() -> u64 {
+    dropped_event_count().load(Ordering::Relaxed)
+}
+
+/// Handle held by the single background task that drains `event_channel` and
+/// forwards batches to the existing tracing backend. Cloning a
+/// `TracingCollector` is cheap; all clones share the same receiver, but only
+/// one task should actually drain it.
+#[derive(Clone)]
+pub struct TracingCollector {
+    receiver: Receiver<TracingEvent>,
+}
+
+impl TracingCollector {
+    pub fn handle() -> Self {
+        Self {
+            receiver: event_channel().1.clone(),
+        }
+    }
+
+    /// Drain whatever events are currently buffered without blocking. Meant
+    /// to be called periodically by the collector's drain loop.
+    pub fn drain_available(&self) -> Vec<TracingEvent> {
+        self.receiver.try_iter().collect()
+    }
 }
 
 impl Display for ClientEntryPoint {
@@ -1,10 +1,24 @@
 // Copyright Facebook, Inc. 2019
 
+use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 
+use failure::format_err;
 use failure::Fallible;
+use serde::Deserialize;
 use url::Url;
 
+/// Environment variables consulted by [`Config::from_env`] /
+/// [`Config::from_toml`], in the style of a long-running daemon configured
+/// via `config.toml` plus env overrides.
+const ENV_API_URL: &str = "MONONOKE_API_URL";
+const ENV_API_CERT: &str = "MONONOKE_API_CERT";
+const ENV_API_KEY: &str = "MONONOKE_API_KEY";
+const ENV_API_REPO: &str = "MONONOKE_API_REPO";
+const ENV_API_CACHE_PATH: &str = "MONONOKE_API_CACHE_PATH";
+const ENV_API_BATCH_SIZE: &str = "MONONOKE_API_BATCH_SIZE";
+
 #[derive(Default)]
 pub struct Config {
     pub(crate) base_url: Option<Url>,
@@ -14,11 +28,86 @@ pub struct Config {
     pub(crate) batch_size: Option<usize>,
 }
 
+/// Shape of the TOML file consumed by [`Config::from_toml`]. Intentionally
+/// mirrors `Config`'s own fields one-to-one, but with `cert`/`key` split out
+/// to match how they're given to [`Config::client_creds`].
+#[derive(Default, Deserialize)]
+struct TomlConfig {
+    base_url: Option<String>,
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+    repo: Option<String>,
+    cache_path: Option<PathBuf>,
+    batch_size: Option<usize>,
+}
+
 impl Config {
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// Load a `Config` from a TOML file with keys `base_url`, `cert`, `key`,
+    /// `repo`, `cache_path`, `batch_size` (all optional), then let any
+    /// `MONONOKE_API_*` environment variables override the file's values.
+    /// Further builder calls on the returned `Config` take precedence over
+    /// both, so callers only need to hand-wire the fields that are neither
+    /// in the file nor the environment.
+    pub fn from_toml(path: impl AsRef<Path>) -> Fallible<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| format_err!("failed to read config file {:?}: {}", path, e))?;
+        let raw: TomlConfig = toml::from_str(&content)
+            .map_err(|e| format_err!("failed to parse config file {:?}: {}", path, e))?;
+
+        let mut config = Config::new();
+        if let Some(base_url) = raw.base_url {
+            config = config.base_url_str(&base_url)?;
+        }
+        config = apply_creds(config, raw.cert, raw.key)?;
+        if let Some(repo) = raw.repo {
+            config = config.repo(repo);
+        }
+        if let Some(cache_path) = raw.cache_path {
+            config = config.cache_path(cache_path);
+        }
+        if let Some(batch_size) = raw.batch_size {
+            config = config.batch_size(Some(batch_size));
+        }
+
+        config.apply_env()
+    }
+
+    /// Load a `Config` purely from `MONONOKE_API_*` environment variables.
+    pub fn from_env() -> Fallible<Self> {
+        Config::new().apply_env()
+    }
+
+    /// Overrides any field already set with the corresponding
+    /// `MONONOKE_API_*` environment variable, if present. Used by both
+    /// `from_env` (on a fresh `Config`) and `from_toml` (layered on top of
+    /// the parsed file).
+    fn apply_env(mut self) -> Fallible<Self> {
+        if let Some(base_url) = read_env(ENV_API_URL) {
+            self = self.base_url_str(&base_url)?;
+        }
+        let cert = read_env(ENV_API_CERT).map(PathBuf::from);
+        let key = read_env(ENV_API_KEY).map(PathBuf::from);
+        self = apply_creds(self, cert, key)?;
+        if let Some(repo) = read_env(ENV_API_REPO) {
+            self = self.repo(repo);
+        }
+        if let Some(cache_path) = read_env(ENV_API_CACHE_PATH) {
+            self = self.cache_path(cache_path);
+        }
+        if let Some(batch_size) = read_env(ENV_API_BATCH_SIZE) {
+            let batch_size = batch_size
+                .parse()
+                .map_err(|e| format_err!("invalid {}: {}", ENV_API_BATCH_SIZE, e))?;
+            self = self.batch_size(Some(batch_size));
+        }
+        Ok(self)
+    }
+
     /// Base URL of the Mononoke API server host.
     pub fn base_url(mut self, url: Url) -> Self {
         self.base_url = Some(url);
@@ -78,3 +167,38 @@ impl ClientCreds {
         }
     }
 }
+
+/// Reads an environment variable, treating it as absent (rather than an
+/// error) both when it is unset and when its value is not valid UTF-8.
+fn read_env(name: &str) -> Option<String> {
+    match env::var(name) {
+        Ok(value) => Some(value),
+        Err(_) => None,
+    }
+}
+
+/// Applies a `cert`/`key` pair to `config` if both are present. Mutual TLS
+/// needs both or neither, but "both" doesn't have to come from the same
+/// source: a lone `cert` (or `key`) is only an error if `config` doesn't
+/// already hold the other half from a previous layer (e.g. `from_toml`
+/// setting both, then `apply_env` overriding just one of them) — otherwise
+/// a previously-valid config would fail to load merely because the
+/// environment only overrides one of the two paths.
+fn apply_creds(
+    config: Config,
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+) -> Fallible<Config> {
+    match (cert, key) {
+        (Some(cert), Some(key)) => Ok(config.client_creds(cert, key)),
+        (Some(cert), None) => match config.creds.as_ref().map(|creds| creds.key.clone()) {
+            Some(key) => Ok(config.client_creds(cert, key)),
+            None => Err(format_err!("client certificate specified without a key")),
+        },
+        (None, Some(key)) => match config.creds.as_ref().map(|creds| creds.certs.clone()) {
+            Some(cert) => Ok(config.client_creds(cert, key)),
+            None => Err(format_err!("client key specified without a certificate")),
+        },
+        (None, None) => Ok(config),
+    }
+}